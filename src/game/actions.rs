@@ -0,0 +1,452 @@
+use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::marker::PhantomData;
+
+/// The controls of every player, the first keeps the name from before there were two.
+const CONTROLS_FILES: [&str; 2] = ["controls.ron", "controls_2.ron"];
+/// How far a stick has to be pushed before its direction counts as pressed.
+const AXIS_PRESS_THRESHOLD: f32 = 0.5;
+
+/// Something the player can do, the keys and buttons for it are looked up in the [`ActionMap`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub(in crate::game) enum Action {
+    Thrust,
+    Reverse,
+    RotateLeft,
+    RotateRight,
+    Fire,
+    Hyperspace,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::Thrust,
+        Action::Reverse,
+        Action::RotateLeft,
+        Action::RotateRight,
+        Action::Fire,
+        Action::Hyperspace,
+        Action::Pause,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Action::Thrust => "Thrust",
+            Action::Reverse => "Reverse",
+            Action::RotateLeft => "Rotate left",
+            Action::RotateRight => "Rotate right",
+            Action::Fire => "Fire",
+            Action::Hyperspace => "Hyperspace",
+            Action::Pause => "Pause",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub(in crate::game) enum Binding {
+    Key(KeyCode),
+    /// The button on any connected gamepad.
+    GamepadButton(GamepadButtonType),
+    /// One direction of a stick on any connected gamepad.
+    GamepadAxis { axis: GamepadAxisType, positive: bool },
+}
+
+impl Binding {
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::GamepadButton(button) => format!("Pad {:?}", button),
+            Binding::GamepadAxis { axis, positive } => format!("Pad {:?}{}", axis, if *positive { "+" } else { "-" }),
+        }
+    }
+
+    fn same_kind(&self, other: &Binding) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+/// The keys and gamepad buttons bound to every action, loaded from `controls.ron`, or
+/// `controls_2.ron` for the second player, when it exists.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(in crate::game) struct ActionMap {
+    bindings: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .iter()
+            .map(|action| {
+                let (key, button) = match action {
+                    Action::Thrust => (KeyCode::W, GamepadButtonType::RightTrigger2),
+                    Action::Reverse => (KeyCode::S, GamepadButtonType::LeftTrigger2),
+                    Action::RotateLeft => (KeyCode::A, GamepadButtonType::DPadLeft),
+                    Action::RotateRight => (KeyCode::D, GamepadButtonType::DPadRight),
+                    Action::Fire => (KeyCode::Space, GamepadButtonType::South),
+                    Action::Hyperspace => (KeyCode::LShift, GamepadButtonType::East),
+                    Action::Pause => (KeyCode::Escape, GamepadButtonType::Start),
+                };
+
+                let mut bindings = vec![Binding::Key(key), Binding::GamepadButton(button)];
+
+                match action {
+                    Action::RotateLeft => bindings.push(Binding::GamepadAxis { axis: GamepadAxisType::LeftStickX, positive: false }),
+                    Action::RotateRight => bindings.push(Binding::GamepadAxis { axis: GamepadAxisType::LeftStickX, positive: true }),
+                    _ => {},
+                }
+
+                (*action, bindings)
+            })
+            .collect();
+
+        ActionMap { bindings }
+    }
+}
+
+impl ActionMap {
+    /// The second ship in two player games, on the arrow keys and the right side of the keyboard.
+    pub fn second_player() -> Self {
+        let mut map = ActionMap::default();
+
+        for (action, key) in [
+            (Action::Thrust, KeyCode::Up),
+            (Action::Reverse, KeyCode::Down),
+            (Action::RotateLeft, KeyCode::Left),
+            (Action::RotateRight, KeyCode::Right),
+            (Action::Fire, KeyCode::RControl),
+            (Action::Hyperspace, KeyCode::RShift),
+        ] {
+            map.rebind(action, Binding::Key(key));
+        }
+
+        map
+    }
+
+    /// The bindings `player` starts out with.
+    pub fn defaults(player: usize) -> Self {
+        match player {
+            0 => ActionMap::default(),
+            _ => ActionMap::second_player(),
+        }
+    }
+
+    /// Falls back to the default bindings when there is no controls file for `player` or it
+    /// cannot be read.
+    pub fn load(player: usize) -> Self {
+        let file = CONTROLS_FILES[player];
+
+        match fs::read_to_string(file) {
+            Ok(text) => ActionMap::from_ron(&text).unwrap_or_else(|error| {
+                warn!("Ignoring {}: {}", file, error);
+                ActionMap::defaults(player)
+            }),
+            Err(_) => ActionMap::defaults(player),
+        }
+    }
+
+    /// Writes the bindings to the controls file of `player`, so they are used again on the next start.
+    pub fn save(&self, player: usize) {
+        let file = CONTROLS_FILES[player];
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| error.to_string())
+            .and_then(|text| fs::write(file, text).map_err(|error| error.to_string()));
+
+        if let Err(error) = result {
+            warn!("Could not save {}: {}", file, error);
+        }
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, ron::Error> {
+        ron::from_str(text)
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Replaces the binding of the same kind, a key replaces a key and a gamepad button a gamepad button.
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+
+        match bindings.iter_mut().find(|current| current.same_kind(&binding)) {
+            Some(current) => *current = binding,
+            None => bindings.push(binding),
+        }
+    }
+
+    /// Bindings used by more than one action, with every action that uses them.
+    pub fn conflicts(&self) -> Vec<(Binding, Vec<Action>)> {
+        let mut conflicts: Vec<(Binding, Vec<Action>)> = Vec::new();
+
+        for (action, bindings) in &self.bindings {
+            for binding in bindings {
+                match conflicts.iter_mut().find(|(other, _)| other == binding) {
+                    Some((_, actions)) => actions.push(*action),
+                    None => conflicts.push((*binding, vec![*action])),
+                }
+            }
+        }
+
+        conflicts.retain(|(_, actions)| actions.len() > 1);
+        conflicts
+    }
+
+    /// Keys bound here that `other` binds as well, with the action they have in `other`. Both
+    /// players share the keyboard, while every player has a gamepad of their own. Either player
+    /// may pause with the same key.
+    pub fn shared_keys(&self, other: &ActionMap) -> Vec<(Binding, Action)> {
+        let mut shared = Vec::new();
+
+        for (action, bindings) in &self.bindings {
+            for binding in bindings.iter().filter(|binding| matches!(binding, Binding::Key(_))) {
+                for (other_action, other_bindings) in &other.bindings {
+                    let both_pause = *action == Action::Pause && *other_action == Action::Pause;
+
+                    if !both_pause && other_bindings.contains(binding) && !shared.contains(&(*binding, *other_action)) {
+                        shared.push((*binding, *other_action));
+                    }
+                }
+            }
+        }
+
+        shared
+    }
+
+    /// Whether `is_down` holds for any of the bindings of `action`.
+    pub fn any_binding(&self, action: Action, is_down: impl Fn(Binding) -> bool) -> bool {
+        self.bindings(action).iter().copied().any(is_down)
+    }
+}
+
+/// Bindings of the second player, changed on the controls screen like those of the first.
+pub(in crate::game) struct SecondPlayerActionMap(pub ActionMap);
+
+/// How the ship is flown, picked in the settings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(in crate::game) enum ControlScheme {
+    /// Turning and thrust relative to where the ship faces.
+    #[default]
+    Tank,
+    /// The ship faces the mouse cursor, fires with the left button and moves across the screen
+    /// with the thrust and rotate actions.
+    MouseAim,
+}
+
+impl ControlScheme {
+    pub fn next(&self) -> ControlScheme {
+        match *self {
+            ControlScheme::Tank => ControlScheme::MouseAim,
+            ControlScheme::MouseAim => ControlScheme::Tank,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ControlScheme::Tank => "Tank",
+            ControlScheme::MouseAim => "Mouse aim",
+        }
+    }
+}
+
+/// Reads actions instead of single keys, for systems that react to the player's controls.
+#[derive(SystemParam)]
+pub(in crate::game) struct Actions<'w, 's> {
+    map: Res<'w, ActionMap>,
+    second_map: Res<'w, SecondPlayerActionMap>,
+    keys: Res<'w, Input<KeyCode>>,
+    buttons: Res<'w, Input<GamepadButton>>,
+    button_axes: Res<'w, Axis<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    gamepads: Res<'w, Gamepads>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> Actions<'w, 's> {
+    /// Whether `action` was just pressed on the first player's controls or any gamepad.
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.player(0, 1).just_pressed(action)
+    }
+
+    /// The controls of player `index` in a game with `player_count` ships. A single player
+    /// listens to every gamepad, otherwise they are shared out by [`assigned_gamepads`].
+    pub fn player(&self, index: usize, player_count: usize) -> PlayerActions<'_> {
+        let mut gamepads: Vec<Gamepad> = self.gamepads.iter().copied().collect();
+        gamepads.sort_unstable_by_key(|gamepad| gamepad.0);
+
+        PlayerActions {
+            map: if index == 0 { &self.map } else { &self.second_map.0 },
+            keys: &self.keys,
+            buttons: &self.buttons,
+            button_axes: &self.button_axes,
+            axes: &self.axes,
+            gamepads: assigned_gamepads(&gamepads, index, player_count),
+        }
+    }
+}
+
+/// The actions of one player, see [`Actions::player`].
+pub(in crate::game) struct PlayerActions<'a> {
+    map: &'a ActionMap,
+    keys: &'a Input<KeyCode>,
+    buttons: &'a Input<GamepadButton>,
+    button_axes: &'a Axis<GamepadButton>,
+    axes: &'a Axis<GamepadAxis>,
+    gamepads: Vec<Gamepad>,
+}
+
+impl<'a> PlayerActions<'a> {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.map.any_binding(action, |binding| match binding {
+            Binding::Key(key) => self.keys.pressed(key),
+            Binding::GamepadButton(button) => self.gamepads.iter().any(|gamepad| self.buttons.pressed(GamepadButton(*gamepad, button))),
+            Binding::GamepadAxis { .. } => self.binding_value(binding) > AXIS_PRESS_THRESHOLD,
+        })
+    }
+
+    /// Sticks are not tracked between frames, so only keys and buttons can be just pressed.
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.map.any_binding(action, |binding| match binding {
+            Binding::Key(key) => self.keys.just_pressed(key),
+            Binding::GamepadButton(button) => self.gamepads.iter().any(|gamepad| self.buttons.just_pressed(GamepadButton(*gamepad, button))),
+            Binding::GamepadAxis { .. } => false,
+        })
+    }
+
+    /// How far the action is pushed from 0.0 to 1.0, triggers and sticks can be pushed part way
+    /// while keys are either released or pushed all the way.
+    pub fn value(&self, action: Action) -> f32 {
+        self.map
+            .bindings(action)
+            .iter()
+            .map(|binding| self.binding_value(*binding))
+            .fold(0.0, f32::max)
+    }
+
+    fn binding_value(&self, binding: Binding) -> f32 {
+        let per_gamepad = |value: &dyn Fn(Gamepad) -> f32| self.gamepads.iter().map(|gamepad| value(*gamepad)).fold(0.0, f32::max);
+
+        match binding {
+            Binding::Key(key) => if self.keys.pressed(key) { 1.0 } else { 0.0 },
+            Binding::GamepadButton(button) => per_gamepad(&|gamepad| {
+                let button = GamepadButton(gamepad, button);
+                // Digital buttons may not report an analog value at all.
+                self.button_axes
+                    .get(button)
+                    .unwrap_or(if self.buttons.pressed(button) { 1.0 } else { 0.0 })
+            }),
+            Binding::GamepadAxis { axis, positive } => per_gamepad(&|gamepad| {
+                let value = self.axes.get(GamepadAxis(gamepad, axis)).unwrap_or(0.0);
+                (if positive { value } else { -value }).clamp(0.0, 1.0)
+            }),
+        }
+    }
+}
+
+/// With a gamepad for everyone each player gets their own. With fewer, the gamepads go to the
+/// last players, so the first ones share the keyboard.
+fn assigned_gamepads(gamepads: &[Gamepad], index: usize, player_count: usize) -> Vec<Gamepad> {
+    if player_count <= 1 {
+        return gamepads.to_vec();
+    }
+
+    let keyboard_players = player_count.saturating_sub(gamepads.len());
+
+    index
+        .checked_sub(keyboard_players)
+        .and_then(|gamepad| gamepads.get(gamepad))
+        .into_iter()
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_action_has_a_default_binding() {
+        let map = ActionMap::default();
+
+        for action in Action::ALL {
+            assert!(!map.bindings(action).is_empty());
+        }
+    }
+
+    #[test]
+    fn bindings_round_trip_through_ron() {
+        let map = ActionMap::default();
+        let text = ron::to_string(&map).unwrap();
+
+        assert!(text.contains("Thrust"));
+        assert_eq!(ActionMap::from_ron(&text).unwrap(), map);
+    }
+
+    #[test]
+    fn rebinding_replaces_only_the_same_kind() {
+        let mut map = ActionMap::default();
+        map.rebind(Action::Fire, Binding::Key(KeyCode::Return));
+
+        assert_eq!(
+            map.bindings(Action::Fire),
+            &[Binding::Key(KeyCode::Return), Binding::GamepadButton(GamepadButtonType::South)]
+        );
+    }
+
+    #[test]
+    fn finds_bindings_shared_by_actions() {
+        let mut map = ActionMap::default();
+        assert!(map.conflicts().is_empty());
+
+        map.rebind(Action::Hyperspace, Binding::Key(KeyCode::Space));
+        assert_eq!(map.conflicts(), vec![(Binding::Key(KeyCode::Space), vec![Action::Fire, Action::Hyperspace])]);
+    }
+
+    #[test]
+    fn finds_keys_bound_for_both_players() {
+        let first = ActionMap::defaults(0);
+        let mut second = ActionMap::defaults(1);
+        assert!(first.shared_keys(&second).is_empty());
+
+        second.rebind(Action::Hyperspace, Binding::Key(KeyCode::Space));
+        assert_eq!(first.shared_keys(&second), vec![(Binding::Key(KeyCode::Space), Action::Hyperspace)]);
+        assert_eq!(second.shared_keys(&first), vec![(Binding::Key(KeyCode::Space), Action::Fire)]);
+    }
+
+    #[test]
+    fn second_player_keeps_the_gamepad_bindings() {
+        let map = ActionMap::defaults(1);
+
+        assert_eq!(map.bindings(Action::Thrust)[0], Binding::Key(KeyCode::Up));
+        assert_eq!(
+            map.bindings(Action::Fire),
+            &[Binding::Key(KeyCode::RControl), Binding::GamepadButton(GamepadButtonType::South)]
+        );
+    }
+
+    #[test]
+    fn gamepads_go_to_the_players_without_a_keyboard() {
+        let pads = [Gamepad(0), Gamepad(1)];
+
+        assert_eq!(assigned_gamepads(&pads, 0, 1), pads.to_vec());
+        assert_eq!(assigned_gamepads(&pads, 0, 2), vec![Gamepad(0)]);
+        assert_eq!(assigned_gamepads(&pads, 1, 2), vec![Gamepad(1)]);
+        assert_eq!(assigned_gamepads(&pads[..1], 0, 2), vec![]);
+        assert_eq!(assigned_gamepads(&pads[..1], 1, 2), vec![Gamepad(0)]);
+        assert_eq!(assigned_gamepads(&[], 1, 2), vec![]);
+    }
+
+    #[test]
+    fn action_is_down_when_any_of_its_bindings_is() {
+        let map = ActionMap::default();
+        let trigger_down = |binding| binding == Binding::GamepadButton(GamepadButtonType::RightTrigger2);
+
+        assert!(map.any_binding(Action::Thrust, trigger_down));
+        assert!(!map.any_binding(Action::Fire, trigger_down));
+        assert!(map.any_binding(Action::Fire, |binding| binding == Binding::Key(KeyCode::Space)));
+    }
+}
@@ -0,0 +1,113 @@
+use bevy::prelude::*;
+use bevy::math::const_vec2;
+
+use super::player::PlayerCamera;
+
+/// Size of the playing field in the classic wrap-around mode.
+pub const ARENA_SIZE: Vec2 = const_vec2!([640.0, 480.0]);
+
+/// Chosen in the menu before a run starts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArenaMode {
+    /// Unbounded space with the camera following the ship.
+    Open,
+    /// Fixed arena with a static camera where everything wraps at the edges.
+    Wrap,
+}
+
+impl ArenaMode {
+    pub fn next(&self) -> ArenaMode {
+        match *self {
+            ArenaMode::Open => ArenaMode::Wrap,
+            ArenaMode::Wrap => ArenaMode::Open,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ArenaMode::Open => "Open space",
+            ArenaMode::Wrap => "Classic",
+        }
+    }
+
+    /// Shortest displacement from `from` to `to`, going across the wrap seam if that is closer.
+    pub fn offset(&self, from: Vec2, to: Vec2) -> Vec2 {
+        let offset = to - from;
+
+        match *self {
+            ArenaMode::Open => offset,
+            ArenaMode::Wrap => Vec2::new(
+                wrap_coordinate(offset.x, ARENA_SIZE.x),
+                wrap_coordinate(offset.y, ARENA_SIZE.y)
+            ),
+        }
+    }
+
+    pub fn distance(&self, a: Vec2, b: Vec2) -> f32 {
+        self.offset(a, b).length()
+    }
+
+    /// Moves a position back into the arena, positions in open space are left untouched.
+    pub fn wrap(&self, translation: Vec3) -> Vec3 {
+        match *self {
+            ArenaMode::Open => translation,
+            ArenaMode::Wrap => Vec3::new(
+                wrap_coordinate(translation.x, ARENA_SIZE.x),
+                wrap_coordinate(translation.y, ARENA_SIZE.y),
+                translation.z
+            ),
+        }
+    }
+}
+
+/// Marks entities that wrap at the arena edges.
+#[derive(Component)]
+pub struct Wrapping;
+
+pub fn wrap_positions(arena_mode: Res<ArenaMode>, mut query: Query<&mut Transform, With<Wrapping>>) {
+
+    if *arena_mode != ArenaMode::Wrap {
+        return;
+    }
+
+    query.for_each_mut(|mut transform| {
+        let wrapped = arena_mode.wrap(transform.translation);
+
+        if wrapped != transform.translation {
+            transform.translation = wrapped;
+        }
+    });
+}
+
+pub fn arena_camera(
+    arena_mode: Res<ArenaMode>,
+    windows: Res<Windows>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<PlayerCamera>>
+) {
+
+    if *arena_mode != ArenaMode::Wrap {
+        return;
+    }
+
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+
+    let scale = (ARENA_SIZE.x / window.width()).max(ARENA_SIZE.y / window.height());
+
+    camera_query.for_each_mut(|(mut transform, mut projection)| {
+        if transform.translation.truncate() != Vec2::ZERO {
+            transform.translation.x = 0.0;
+            transform.translation.y = 0.0;
+        }
+
+        if projection.scale != scale {
+            projection.scale = scale;
+        }
+    });
+}
+
+fn wrap_coordinate(value: f32, size: f32) -> f32 {
+    (value + size / 2.0).rem_euclid(size) - size / 2.0
+}
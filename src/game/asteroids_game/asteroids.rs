@@ -2,12 +2,110 @@ use bevy::prelude::*;
 use rand::prelude::*;
 use std::time::Duration;
 use std::f32::consts::PI;
-use super::player::Player;
+use super::arena::{ArenaMode, Wrapping, ARENA_SIZE};
+use super::collision::Collider;
+use super::player::{self, MatchMode, Player, PlayerLives, MAX_PLAYERS};
+use super::rng::GameRng;
+use super::simulation::{FixedTime, Interpolated};
+use super::spatial_hash::SpatialHash;
+use super::waves::Waves;
+
+const FRAGMENT_SPEED_FACTOR: f32 = 1.4;
+const FRAGMENT_SPREAD: f32 = 0.6;
+const SPIN_TRANSFER: f32 = 0.5;
+const MAX_SPIN: f32 = 2.0 * PI;
+/// Versus obstacles start at most this far left or right of the middle of the arena.
+const OBSTACLE_SPREAD: f32 = 60.0;
+const OBSTACLE_MIN_SPEED: f32 = 15.0;
+const OBSTACLE_MAX_SPEED: f32 = 30.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AsteroidSize {
+    Large,
+    Medium,
+    Small,
+}
+
+impl AsteroidSize {
+    pub fn radius(&self) -> f32 {
+        match *self {
+            AsteroidSize::Large => 24.0,
+            AsteroidSize::Medium => 14.0,
+            AsteroidSize::Small => 8.0,
+        }
+    }
+
+    pub fn sprite_size(&self) -> f32 {
+        match *self {
+            AsteroidSize::Large => 48.0,
+            AsteroidSize::Medium => 28.0,
+            AsteroidSize::Small => 16.0,
+        }
+    }
+
+    pub fn score(&self) -> u32 {
+        match *self {
+            AsteroidSize::Large => 20,
+            AsteroidSize::Medium => 50,
+            AsteroidSize::Small => 100,
+        }
+    }
+
+    /// Mass used for bouncing asteroids off each other, grows with the covered area.
+    pub fn mass(&self) -> f32 {
+        self.radius() * self.radius()
+    }
+
+    /// The size of the fragments this asteroid breaks into, `None` if it is destroyed entirely.
+    pub fn fragment_size(&self) -> Option<AsteroidSize> {
+        match *self {
+            AsteroidSize::Large => Some(AsteroidSize::Medium),
+            AsteroidSize::Medium => Some(AsteroidSize::Small),
+            AsteroidSize::Small => None,
+        }
+    }
+}
 
 #[derive(Component, Clone, Copy)]
 pub struct Asteroid {
     velocity: Vec2,
     rotation: f32,
+    size: AsteroidSize,
+    /// Counts up with every asteroid spawned in a run, unlike the entity ids it is the same in
+    /// a replay of the run.
+    serial: u32,
+    /// Set as soon as something breaks it, it is only despawned at the end of the stage.
+    broken: bool,
+}
+
+impl Asteroid {
+    /// Numbered once it is spawned.
+    fn new(velocity: Vec2, rotation: f32, size: AsteroidSize) -> Self {
+        Asteroid {
+            velocity,
+            rotation,
+            size,
+            serial: 0,
+            broken: false,
+        }
+    }
+
+    pub fn size(&self) -> AsteroidSize {
+        self.size
+    }
+
+    pub fn velocity(&self) -> Vec2 {
+        self.velocity
+    }
+
+    pub fn broken(&self) -> bool {
+        self.broken
+    }
+
+    /// Keeps every other collision from breaking it again in the same step.
+    pub fn mark_broken(&mut self) {
+        self.broken = true;
+    }
 }
 
 pub struct AsteroidsAtlas {
@@ -15,21 +113,74 @@ pub struct AsteroidsAtlas {
 }
 
 pub struct AsteroidsStats {
-    target_number: u32,
     current_number: u32,
+    spawned_number: u32,
     destroyed_number: u32,
-    spawn_timer: Timer,
+    /// Points of every player, kills are credited to whoever made them.
+    scores: [u32; MAX_PLAYERS],
+    shots_fired: u32,
+    shots_hit: u32,
+    elapsed: Duration,
 }
 
 impl AsteroidsStats {
-    pub fn destroyed(&mut self) {
-        self.current_number -= 1;
+    /// `player` destroyed an asteroid, by shooting it or flying into it.
+    pub fn destroyed(&mut self, size: AsteroidSize, player: Option<usize>) {
+        self.current_number = self.current_number.saturating_sub(1);
         self.destroyed_number += 1;
+
+        if let Some(player) = player {
+            self.scores[player] += size.score();
+        }
+    }
+
+    /// Points for anything that is not an asteroid, like saucers.
+    pub fn bonus(&mut self, player: usize, points: u32) {
+        self.scores[player] += points;
+    }
+
+    /// An asteroid left the field without being destroyed.
+    pub fn removed(&mut self) {
+        self.current_number = self.current_number.saturating_sub(1);
+    }
+
+    pub fn current_number(&self) -> u32 {
+        self.current_number
     }
 
     pub fn destroyed_number(&self) -> u32 {
         self.destroyed_number
     }
+
+    /// The points of all players together.
+    pub fn score(&self) -> u32 {
+        self.scores.iter().sum()
+    }
+
+    pub fn player_score(&self, player: usize) -> u32 {
+        self.scores[player]
+    }
+
+    pub fn shot_fired(&mut self) {
+        self.shots_fired += 1;
+    }
+
+    pub fn shot_hit(&mut self) {
+        self.shots_hit += 1;
+    }
+
+    /// Share of fired lasers that hit an asteroid, between 0.0 and 1.0.
+    pub fn accuracy(&self) -> f32 {
+        if self.shots_fired == 0 {
+            0.0
+        } else {
+            self.shots_hit as f32 / self.shots_fired as f32
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
 }
 
 pub fn asteroids_setup(
@@ -45,10 +196,13 @@ pub fn asteroids_setup(
     commands
         .insert_resource(
             AsteroidsStats {
-                target_number: 1,
                 current_number: 0,
+                spawned_number: 0,
                 destroyed_number: 0,
-                spawn_timer: Timer::from_seconds(5.0, true),
+                scores: [0; MAX_PLAYERS],
+                shots_fired: 0,
+                shots_hit: 0,
+                elapsed: Duration::ZERO,
             });
 }
 
@@ -63,74 +217,179 @@ pub fn remove_asteroids_atlas(
     commands.remove_resource::<AsteroidsStats>();
 }
 
-pub fn asteroid_number_timer(mut asteroids_stats: ResMut<AsteroidsStats>, time: Res<Time>) {
-    asteroids_stats.spawn_timer.tick(time.delta());
-
-    if asteroids_stats.spawn_timer.finished() {
-        // Logic to shorten time or something
-        asteroids_stats.spawn_timer.set_duration(Duration::from_secs(5));
-
-        asteroids_stats.target_number += 1;
-    }
+pub fn survival_timer(mut asteroids_stats: ResMut<AsteroidsStats>, time: Res<FixedTime>) {
+    asteroids_stats.elapsed += time.delta();
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_asteroid(
     mut commands: Commands,
     asteroids_atlas: Res<AsteroidsAtlas>,
     mut asteroids_stats: ResMut<AsteroidsStats>,
-    query: Query<&Transform, With<Player>>
+    mut waves: ResMut<Waves>,
+    query: Query<(&Transform, &Player, &PlayerLives)>,
+    arena_mode: Res<ArenaMode>,
+    match_mode: Res<MatchMode>,
+    mut rng: ResMut<GameRng>,
+    time: Res<FixedTime>
 ) {
 
-    if asteroids_stats.current_number < asteroids_stats.target_number {
+    // Versus rounds bring their own asteroids, see `spawn_obstacles`.
+    if *match_mode == MatchMode::Versus {
+        return;
+    }
+
+    if waves.spawn_due(time.delta()) {
 
-        let player_translation = query.single().translation;
-        let mut rng = rand::thread_rng();
+        let wave = waves.definition();
+        let player_translation = player::random_player_translation(&query, &mut rng);
 
         let offset_angle = rng.gen_range(0.0..2.0*PI);
         let asteroid_offset = vec2_from_circle(offset_angle, rng.gen_range(200.0..300.0));
-        let translation = player_translation + Vec3::new(asteroid_offset.x, asteroid_offset.y, 0.5);
+        let translation = arena_mode.wrap(player_translation + Vec3::new(asteroid_offset.x, asteroid_offset.y, 0.5));
 
         let rotation = rng.gen_range(-0.7..0.7);
         let angle = offset_angle + PI + rng.gen_range(-0.5..0.5);
-        let speed = rng.gen_range(40.0..80.0);
+        let speed = wave.random_speed(&mut *rng);
         let velocity = vec2_from_circle(angle, speed);
+        let size = wave.random_size(&mut *rng);
 
-        commands
-            .spawn_bundle(SpriteSheetBundle {
-                texture_atlas: asteroids_atlas.atlas_handle.clone(),
-                sprite: TextureAtlasSprite {
-                    index: rand::thread_rng().gen_range(0..3),
-                    custom_size: Some(Vec2::new(48.0, 48.0)),
-                    ..Default::default()
-                },
-                transform: Transform {
-                    translation,
-                    ..Default::default()
-                },
-                ..Default::default()
-            })
-            .insert(Asteroid {
-                velocity,
-                rotation,
-            });
+        spawn_asteroid_entity(
+            &mut commands,
+            &asteroids_atlas,
+            &mut asteroids_stats,
+            &mut *rng,
+            Asteroid::new(velocity, rotation, size),
+            translation
+        );
+    }
+
+}
+
+/// Scatters `count` large, slow asteroids down the middle of the arena, between the ships of a versus round.
+pub fn spawn_obstacles(
+    commands: &mut Commands,
+    asteroids_atlas: &AsteroidsAtlas,
+    asteroids_stats: &mut AsteroidsStats,
+    rng: &mut GameRng,
+    count: u32
+) {
+
+    for _ in 0..count {
+        let translation = Vec3::new(
+            rng.gen_range(-OBSTACLE_SPREAD..OBSTACLE_SPREAD),
+            rng.gen_range(-ARENA_SIZE.y / 2.0..ARENA_SIZE.y / 2.0),
+            0.5
+        );
+        let velocity = vec2_from_circle(rng.gen_range(0.0..2.0*PI), rng.gen_range(OBSTACLE_MIN_SPEED..OBSTACLE_MAX_SPEED));
+        let rotation = rng.gen_range(-0.7..0.7);
 
-        asteroids_stats.current_number += 1;
+        spawn_asteroid_entity(
+            commands,
+            asteroids_atlas,
+            asteroids_stats,
+            rng,
+            Asteroid::new(velocity, rotation, AsteroidSize::Large),
+            translation
+        );
     }
+}
 
+/// Breaks a destroyed asteroid into two or three smaller and faster fragments flying apart.
+pub fn spawn_fragments(
+    commands: &mut Commands,
+    asteroids_atlas: &AsteroidsAtlas,
+    asteroids_stats: &mut AsteroidsStats,
+    rng: &mut GameRng,
+    asteroid: &Asteroid,
+    translation: Vec3
+) {
+
+    let fragment_size = match asteroid.size.fragment_size() {
+        Some(size) => size,
+        None => return,
+    };
+
+    let fragment_count = rng.gen_range(2..=3);
+
+    let base_angle = (-asteroid.velocity.x).atan2(asteroid.velocity.y);
+    let speed = asteroid.velocity.length().max(40.0) * FRAGMENT_SPEED_FACTOR;
+
+    for i in 0..fragment_count {
+        let spread = (i as f32 - (fragment_count - 1) as f32 / 2.0) * FRAGMENT_SPREAD * 2.0;
+        let angle = base_angle + spread + rng.gen_range(-0.2..0.2);
+        let velocity = vec2_from_circle(angle, speed * rng.gen_range(0.9..1.1));
+
+        let offset = vec2_from_circle(angle, fragment_size.radius());
+        let fragment_translation = translation + Vec3::new(offset.x, offset.y, 0.0);
+        let rotation = asteroid.rotation * rng.gen_range(1.0..2.0);
+
+        spawn_asteroid_entity(
+            commands,
+            asteroids_atlas,
+            asteroids_stats,
+            rng,
+            Asteroid::new(velocity, rotation, fragment_size),
+            fragment_translation
+        );
+    }
+}
+
+fn spawn_asteroid_entity(
+    commands: &mut Commands,
+    asteroids_atlas: &AsteroidsAtlas,
+    asteroids_stats: &mut AsteroidsStats,
+    rng: &mut impl Rng,
+    mut asteroid: Asteroid,
+    translation: Vec3
+) {
+
+    asteroid.serial = asteroids_stats.spawned_number;
+    asteroids_stats.spawned_number += 1;
+    asteroids_stats.current_number += 1;
+
+    let sprite_size = asteroid.size.sprite_size();
+
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            texture_atlas: asteroids_atlas.atlas_handle.clone(),
+            sprite: TextureAtlasSprite {
+                index: rng.gen_range(0..3),
+                custom_size: Some(Vec2::new(sprite_size, sprite_size)),
+                ..Default::default()
+            },
+            transform: Transform {
+                translation,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(asteroid)
+        .insert(Collider::circle(asteroid.size.radius()))
+        .insert(Interpolated::new(Transform::from_translation(translation)))
+        .insert(Wrapping);
 }
 
 pub fn asteroid_distance_cleanup(
     mut commands: Commands,
     asteroid_query: Query<(Entity, &Transform), With<Asteroid>>,
-    player_query: Query<&Transform, With<Player>>
+    player_query: Query<(&Transform, &PlayerLives), With<Player>>,
+    mut asteroids_stats: ResMut<AsteroidsStats>,
+    arena_mode: Res<ArenaMode>
 ) {
 
-    if !asteroid_query.is_empty() {
-        let player_translation = player_query.single().translation;
-
+    // Asteroids never leave the classic arena, they wrap around instead.
+    if *arena_mode == ArenaMode::Open && !asteroid_query.is_empty() {
         asteroid_query.for_each(|(entity, transform)| {
-            if player_translation.distance(transform.translation) > 400.0 {
+            // Ships out of lives are left where they went down, the game goes on around the others.
+            let far_from_everyone = player_query
+                .iter()
+                .filter(|(_, player_lives)| player_lives.lives() > 0)
+                .all(|(player_transform, _)| player_transform.translation.distance(transform.translation) > 400.0);
+
+            if far_from_everyone {
                 commands.entity(entity).despawn();
+                asteroids_stats.removed();
             }
         });
     }
@@ -142,20 +401,196 @@ pub fn remove_asteroids(mut commands: Commands, query: Query<Entity, With<Astero
     });
 }
 
-pub fn asteroid_rotation(mut asteroid_query: Query<(&mut Transform, &Asteroid)>, time: Res<Time>) {
+pub fn asteroid_rotation(mut asteroid_query: Query<(&mut Transform, &Asteroid)>, time: Res<FixedTime>) {
     asteroid_query.for_each_mut(|(mut transform, asteroid)| {
         let rotation = Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), asteroid.rotation * time.delta_seconds());
         transform.rotation = transform.rotation.mul_quat(rotation);
     });
 }
 
-pub fn asteroid_movement(mut asteroid_query: Query<(&mut Transform, &Asteroid)>, time: Res<Time>) {
+pub fn asteroid_movement(mut asteroid_query: Query<(&mut Transform, &Asteroid)>, time: Res<FixedTime>) {
     asteroid_query.for_each_mut(|(mut transform, asteroid)| {
         transform.translation.x += asteroid.velocity.x * time.delta_seconds();
         transform.translation.y += asteroid.velocity.y * time.delta_seconds();
     });
 }
 
+pub fn asteroid_collision(
+    mut asteroid_query: Query<(Entity, &mut Transform, &mut Asteroid)>,
+    spatial_hash: Res<SpatialHash>,
+    arena_mode: Res<ArenaMode>
+) {
+
+    let mut pairs: Vec<((u32, u32), (Entity, Entity))> = asteroid_query
+        .iter()
+        .flat_map(|(entity, transform, asteroid)| {
+            let asteroid_query = &asteroid_query;
+
+            spatial_hash
+                .nearby(transform.translation.truncate(), asteroid.size.radius())
+                .into_iter()
+                .filter_map(move |other| {
+                    // Other colliders near the asteroid are not asteroids.
+                    let (_, _, other_asteroid) = asteroid_query.get(other).ok()?;
+                    (other_asteroid.serial > asteroid.serial).then_some(((asteroid.serial, other_asteroid.serial), (entity, other)))
+                })
+        })
+        .collect();
+
+    // Resolve the pairs in the same order every time, no matter where they were found. Entity ids
+    // are handed out differently when a run is replayed, the serials are not.
+    pairs.sort_unstable_by_key(|(serials, _)| *serials);
+
+    for (_, (entity_a, entity_b)) in pairs {
+        let [(_, mut transform_a, mut asteroid_a), (_, mut transform_b, mut asteroid_b)] = match asteroid_query.get_many_mut([entity_a, entity_b]) {
+            Ok(asteroids) => asteroids,
+            Err(_) => continue,
+        };
+
+        let offset = arena_mode.offset(transform_a.translation.truncate(), transform_b.translation.truncate());
+
+        if offset.length() >= asteroid_a.size.radius() + asteroid_b.size.radius() {
+            continue;
+        }
+
+        let (separation_a, separation_b) = resolve_collision(&mut asteroid_a, &mut asteroid_b, offset);
+
+        transform_a.translation += separation_a.extend(0.0);
+        transform_b.translation += separation_b.extend(0.0);
+    }
+}
+
+/// Bounces two touching asteroids off each other, `offset` points from `a` to `b`.
+///
+/// The velocities along the line between the centers are exchanged like in an elastic
+/// collision of the two masses, the sliding part of the impact adds spin. Returns how far
+/// `a` and `b` have to be moved so they no longer overlap.
+fn resolve_collision(a: &mut Asteroid, b: &mut Asteroid, offset: Vec2) -> (Vec2, Vec2) {
+
+    let distance = offset.length();
+    let normal = if distance > f32::EPSILON { offset / distance } else { Vec2::Y };
+
+    let mass_a = a.size.mass();
+    let mass_b = b.size.mass();
+    let total_mass = mass_a + mass_b;
+
+    // Heavier asteroids get pushed aside less.
+    let overlap = (a.size.radius() + b.size.radius() - distance).max(0.0);
+    let separation_a = -normal * overlap * mass_b / total_mass;
+    let separation_b = normal * overlap * mass_a / total_mass;
+
+    let relative_velocity = b.velocity - a.velocity;
+    let approach_speed = relative_velocity.dot(normal);
+
+    // Asteroids that are already moving apart only need to be separated.
+    if approach_speed < 0.0 {
+        let impulse = 2.0 * approach_speed / total_mass;
+        a.velocity += normal * impulse * mass_b;
+        b.velocity -= normal * impulse * mass_a;
+
+        let tangent = normal.perp();
+        let sliding_speed = relative_velocity.dot(tangent);
+        a.rotation = (a.rotation + sliding_speed * SPIN_TRANSFER / a.size.radius()).clamp(-MAX_SPIN, MAX_SPIN);
+        b.rotation = (b.rotation + sliding_speed * SPIN_TRANSFER / b.size.radius()).clamp(-MAX_SPIN, MAX_SPIN);
+    }
+
+    (separation_a, separation_b)
+}
+
 fn vec2_from_circle(angle: f32, radius: f32) -> Vec2 {
     Vec2::new(-angle.sin() * radius, angle.cos() * radius)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asteroid(size: AsteroidSize, velocity: Vec2) -> Asteroid {
+        Asteroid::new(velocity, 0.0, size)
+    }
+
+    fn momentum(asteroid: &Asteroid) -> Vec2 {
+        asteroid.velocity * asteroid.size.mass()
+    }
+
+    fn energy(asteroid: &Asteroid) -> f32 {
+        0.5 * asteroid.size.mass() * asteroid.velocity.length_squared()
+    }
+
+    #[test]
+    fn equal_masses_swap_velocities_head_on() {
+        let mut a = asteroid(AsteroidSize::Medium, Vec2::new(30.0, 0.0));
+        let mut b = asteroid(AsteroidSize::Medium, Vec2::new(-10.0, 0.0));
+
+        resolve_collision(&mut a, &mut b, Vec2::new(27.0, 0.0));
+
+        assert!((a.velocity - Vec2::new(-10.0, 0.0)).length() < 1e-4);
+        assert!((b.velocity - Vec2::new(30.0, 0.0)).length() < 1e-4);
+        assert_eq!(a.rotation, 0.0);
+        assert_eq!(b.rotation, 0.0);
+    }
+
+    #[test]
+    fn collision_conserves_momentum_and_energy() {
+        let mut a = asteroid(AsteroidSize::Large, Vec2::new(20.0, 5.0));
+        let mut b = asteroid(AsteroidSize::Small, Vec2::new(-40.0, 12.0));
+
+        let momentum_before = momentum(&a) + momentum(&b);
+        let energy_before = energy(&a) + energy(&b);
+
+        resolve_collision(&mut a, &mut b, Vec2::new(25.0, 15.0));
+
+        assert!((momentum(&a) + momentum(&b) - momentum_before).length() < 1e-1);
+        assert!((energy(&a) + energy(&b) - energy_before).abs() / energy_before < 1e-4);
+    }
+
+    #[test]
+    fn lighter_asteroid_bounces_off_harder_and_is_pushed_further() {
+        let mut large = asteroid(AsteroidSize::Large, Vec2::ZERO);
+        let mut small = asteroid(AsteroidSize::Small, Vec2::new(-50.0, 0.0));
+
+        let (separation_large, separation_small) = resolve_collision(&mut large, &mut small, Vec2::new(30.0, 0.0));
+
+        assert!(small.velocity.x > 0.0);
+        assert!(large.velocity.x < 0.0);
+        assert!(large.velocity.length() < small.velocity.length());
+        assert!(separation_small.length() > separation_large.length());
+        assert!((separation_small.x - separation_large.x - 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn separating_asteroids_keep_their_velocities() {
+        let mut a = asteroid(AsteroidSize::Medium, Vec2::new(-10.0, 0.0));
+        let mut b = asteroid(AsteroidSize::Medium, Vec2::new(10.0, 0.0));
+
+        let (separation_a, separation_b) = resolve_collision(&mut a, &mut b, Vec2::new(20.0, 0.0));
+
+        assert_eq!(a.velocity, Vec2::new(-10.0, 0.0));
+        assert_eq!(b.velocity, Vec2::new(10.0, 0.0));
+        assert!(separation_a.x < 0.0 && separation_b.x > 0.0);
+    }
+
+    #[test]
+    fn glancing_impact_changes_spin() {
+        let mut a = asteroid(AsteroidSize::Medium, Vec2::new(20.0, 20.0));
+        let mut b = asteroid(AsteroidSize::Medium, Vec2::ZERO);
+
+        resolve_collision(&mut a, &mut b, Vec2::new(27.0, 0.0));
+
+        assert!(a.rotation != 0.0);
+        assert!(b.rotation != 0.0);
+        assert!(a.rotation.abs() <= MAX_SPIN && b.rotation.abs() <= MAX_SPIN);
+    }
+
+    #[test]
+    fn collision_response_is_deterministic() {
+        let run = || {
+            let mut a = asteroid(AsteroidSize::Large, Vec2::new(13.0, -7.0));
+            let mut b = asteroid(AsteroidSize::Medium, Vec2::new(-21.0, 4.0));
+            let separation = resolve_collision(&mut a, &mut b, Vec2::new(20.0, 18.0));
+            (a.velocity, a.rotation, b.velocity, b.rotation, separation)
+        };
+
+        assert_eq!(run(), run());
+    }
+}
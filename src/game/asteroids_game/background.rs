@@ -1,23 +1,256 @@
 use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use rand::prelude::*;
 
-pub fn spawn_background(mut commands: Commands, asset_server: Res<AssetServer>) {
-
-    let texture_handle = asset_server.load("images/stars.png");
-
-    commands
-        .spawn_bundle(SpriteBundle {
-            sprite: Sprite {
-                color: Color::WHITE,
-                custom_size: Some(Vec2::new(256.0, 256.0)),
-                anchor: bevy::sprite::Anchor::Center,
-                ..Default::default()
-            },
-            transform: Transform {
-                translation: Vec3::new(0.0, 0.0, 0.0),
-                ..Default::default()
-            },
-            texture: texture_handle,
-            ..Default::default()
+use super::player::PlayerCamera;
+
+/// Width and height in texels of the generated star tiles.
+const GENERATED_TILE_TEXELS: u32 = 128;
+
+enum StarTexture {
+    Asset(&'static str),
+    /// Transparent tile with `count` square stars of `size` texels, so the layers behind show through.
+    Generated { count: u32, size: u32, seed: u64 },
+}
+
+/// One depth of the starfield, the tiles of a layer repeat endlessly around the camera.
+struct StarLayer {
+    texture: StarTexture,
+    tile_size: f32,
+    /// How far the stars move across the screen for every unit the camera moves, far layers
+    /// barely move.
+    scroll_factor: f32,
+    z: f32,
+}
+
+const LAYERS: [StarLayer; 3] = [
+    StarLayer {
+        texture: StarTexture::Asset("images/stars.png"),
+        tile_size: 256.0,
+        scroll_factor: 0.2,
+        z: 0.0,
+    },
+    StarLayer {
+        texture: StarTexture::Generated { count: 24, size: 1, seed: 1 },
+        tile_size: 320.0,
+        scroll_factor: 0.5,
+        z: 0.1,
+    },
+    StarLayer {
+        texture: StarTexture::Generated { count: 8, size: 2, seed: 2 },
+        tile_size: 416.0,
+        scroll_factor: 0.8,
+        z: 0.2,
+    },
+];
+
+/// Textures of the layers and how many tiles each of them currently has.
+pub struct Starfield {
+    textures: Vec<Handle<Image>>,
+    grids: Vec<UVec2>,
+}
+
+#[derive(Component)]
+pub struct StarTile {
+    layer: usize,
+    column: u32,
+    row: u32,
+}
+
+pub fn spawn_background(mut commands: Commands, asset_server: Res<AssetServer>, mut images: ResMut<Assets<Image>>) {
+
+    let textures = LAYERS
+        .iter()
+        .map(|layer| match layer.texture {
+            StarTexture::Asset(path) => asset_server.load(path),
+            StarTexture::Generated { count, size, seed } => images.add(star_tile(count, size, seed)),
+        })
+        .collect();
+
+    // The tiles are laid out by `scroll_starfield` once it knows how much the camera shows.
+    commands.insert_resource(Starfield {
+        textures,
+        grids: vec![UVec2::ZERO; LAYERS.len()],
+    });
+}
+
+pub fn remove_background(
+    mut commands: Commands,
+    starfield: Res<Starfield>,
+    mut images: ResMut<Assets<Image>>,
+    query: Query<Entity, With<StarTile>>
+) {
+    query.for_each(|entity| {
+        commands.entity(entity).despawn();
+    });
+
+    for (layer, texture) in LAYERS.iter().zip(&starfield.textures) {
+        if let StarTexture::Generated { .. } = layer.texture {
+            images.remove(texture);
+        }
+    }
+
+    commands.remove_resource::<Starfield>();
+}
+
+/// Moves the tiles of every layer to the cells around the camera, adding or dropping tiles when
+/// the camera zooms. Runs after the camera has been interpolated, so the stars never lag a frame behind.
+#[allow(clippy::type_complexity)]
+pub fn scroll_starfield(
+    mut commands: Commands,
+    starfield: Option<ResMut<Starfield>>,
+    camera_query: Query<(&Transform, &OrthographicProjection), (With<PlayerCamera>, Without<StarTile>)>,
+    mut tile_query: Query<(Entity, &StarTile, &mut Transform)>
+) {
+
+    let mut starfield = match starfield {
+        Some(starfield) => starfield,
+        None => return,
+    };
+
+    let (camera_transform, projection) = match camera_query.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+
+    let camera = camera_transform.translation.truncate();
+    let view = Vec2::new(projection.right - projection.left, projection.top - projection.bottom) * projection.scale;
+
+    for (index, layer) in LAYERS.iter().enumerate() {
+        let grid = layer_grid(view, layer.tile_size);
+
+        if starfield.grids[index] == grid {
+            continue;
+        }
+
+        tile_query.for_each(|(entity, tile, _)| {
+            if tile.layer == index {
+                commands.entity(entity).despawn();
+            }
         });
 
+        for column in 0..grid.x {
+            for row in 0..grid.y {
+                commands
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            custom_size: Some(Vec2::splat(layer.tile_size)),
+                            ..Default::default()
+                        },
+                        transform: Transform::from_translation(tile_translation(layer, camera, view, column, row)),
+                        texture: starfield.textures[index].clone(),
+                        ..Default::default()
+                    })
+                    .insert(StarTile { layer: index, column, row });
+            }
+        }
+
+        starfield.grids[index] = grid;
+    }
+
+    tile_query.for_each_mut(|(_, tile, mut transform)| {
+        let translation = tile_translation(&LAYERS[tile.layer], camera, view, tile.column, tile.row);
+
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+    });
+}
+
+/// Columns and rows of tiles needed to cover `view` wherever the camera is.
+fn layer_grid(view: Vec2, tile_size: f32) -> UVec2 {
+    // One more than fits, the view rarely lines up with the tile edges.
+    (view / tile_size).ceil().as_uvec2() + UVec2::ONE
+}
+
+/// Center of the tile in `column` and `row` of the grid covering the view around `camera`.
+fn tile_translation(layer: &StarLayer, camera: Vec2, view: Vec2, column: u32, row: u32) -> Vec3 {
+    // The layer is dragged along with the camera, only the part it is not dragged scrolls by.
+    let layer_offset = camera * (1.0 - layer.scroll_factor);
+    let first_cell = ((camera - layer_offset - view / 2.0) / layer.tile_size).floor();
+    let cell = first_cell + Vec2::new(column as f32, row as f32);
+
+    (layer_offset + (cell + Vec2::splat(0.5)) * layer.tile_size).extend(layer.z)
+}
+
+/// A transparent tile sprinkled with stars, seeded so it looks the same in every game.
+fn star_tile(count: u32, size: u32, seed: u64) -> Image {
+    let texels = GENERATED_TILE_TEXELS;
+    let mut data = vec![0; (texels * texels * 4) as usize];
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    for _ in 0..count {
+        let x = rng.gen_range(0..=texels - size);
+        let y = rng.gen_range(0..=texels - size);
+        let brightness = rng.gen_range(150..=255);
+        // Some stars are slightly blue, some slightly yellow.
+        let tint: i32 = rng.gen_range(-30..=30);
+        let color = [
+            (brightness + tint.min(0)).clamp(0, 255) as u8,
+            brightness as u8,
+            (brightness - tint.max(0)).clamp(0, 255) as u8,
+            255,
+        ];
+
+        for texel_y in y..y + size {
+            for texel_x in x..x + size {
+                let start = ((texel_y * texels + texel_x) * 4) as usize;
+                data[start..start + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    Image::new(
+        Extent3d {
+            width: texels,
+            height: texels,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_tiles_cover_the_view_wherever_the_camera_is() {
+        let view = Vec2::new(520.0, 390.0);
+
+        for layer in &LAYERS {
+            let grid = layer_grid(view, layer.tile_size);
+
+            for camera in [Vec2::ZERO, Vec2::new(1234.5, -987.6), Vec2::new(-50_000.0, 31_000.25)] {
+                let centers: Vec<Vec2> = (0..grid.x)
+                    .flat_map(|column| (0..grid.y).map(move |row| (column, row)))
+                    .map(|(column, row)| tile_translation(layer, camera, view, column, row).truncate())
+                    .collect();
+
+                let half_tile = Vec2::splat(layer.tile_size / 2.0);
+                let min = centers.iter().copied().fold(Vec2::splat(f32::MAX), Vec2::min) - half_tile;
+                let max = centers.iter().copied().fold(Vec2::splat(f32::MIN), Vec2::max) + half_tile;
+
+                assert!(min.cmple(camera - view / 2.0).all() && max.cmpge(camera + view / 2.0).all());
+            }
+        }
+    }
+
+    #[test]
+    fn far_layers_scroll_slower() {
+        let view = Vec2::new(400.0, 300.0);
+        let step = Vec2::new(10.0, 0.0);
+
+        // The tile stays in the same cell for this small a step, so its movement on screen is the scroll.
+        let scrolled = |layer: &StarLayer| {
+            let before = tile_translation(layer, Vec2::ZERO, view, 0, 0).truncate();
+            let after = tile_translation(layer, step, view, 0, 0).truncate();
+            (step - (after - before)).x
+        };
+
+        assert!(scrolled(&LAYERS[0]) < scrolled(&LAYERS[1]));
+        assert!(scrolled(&LAYERS[1]) < scrolled(&LAYERS[2]));
+    }
 }
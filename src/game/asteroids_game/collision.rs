@@ -0,0 +1,299 @@
+use bevy::prelude::*;
+
+use super::arena::ArenaMode;
+
+#[derive(Clone, PartialEq)]
+enum ColliderShape {
+    Circle { radius: f32 },
+    /// A line along the local y axis, rounded off with `radius`.
+    Capsule { half_length: f32, radius: f32 },
+    /// Corners of a convex polygon in local space, counterclockwise.
+    Polygon(Vec<Vec2>),
+}
+
+/// Collision volume of an entity, it follows the translation and rotation of its `Transform`.
+#[derive(Component, Clone, PartialEq)]
+pub struct Collider {
+    shape: ColliderShape,
+}
+
+impl Collider {
+    pub fn circle(radius: f32) -> Self {
+        Collider {
+            shape: ColliderShape::Circle { radius },
+        }
+    }
+
+    pub fn capsule(half_length: f32, radius: f32) -> Self {
+        Collider {
+            shape: ColliderShape::Capsule { half_length, radius },
+        }
+    }
+
+    /// The convex hull of `points` is used, so they can be given in any order.
+    pub fn polygon(points: Vec<Vec2>) -> Self {
+        Collider {
+            shape: ColliderShape::Polygon(convex_hull(points)),
+        }
+    }
+
+    /// Radius of a circle around the entity's translation that contains the whole shape.
+    pub fn bounding_radius(&self) -> f32 {
+        match &self.shape {
+            ColliderShape::Circle { radius } => *radius,
+            ColliderShape::Capsule { half_length, radius } => half_length + radius,
+            ColliderShape::Polygon(points) => points.iter().map(|point| point.length()).fold(0.0, f32::max),
+        }
+    }
+
+    pub fn overlaps(&self, transform: &Transform, other: &Collider, other_transform: &Transform, arena_mode: &ArenaMode) -> bool {
+        self.swept_overlaps(transform, Vec2::ZERO, other, other_transform, arena_mode)
+    }
+
+    /// Like [`Collider::overlaps`], but covers the whole area this collider passed through
+    /// while moving by `displacement` to where it is now, so fast objects cannot skip
+    /// over thin ones between two frames.
+    pub fn swept_overlaps(
+        &self,
+        transform: &Transform,
+        displacement: Vec2,
+        other: &Collider,
+        other_transform: &Transform,
+        arena_mode: &ArenaMode
+    ) -> bool {
+
+        let position = transform.translation.truncate();
+        // Place the other collider on the same side of the wrap seam.
+        let other_position = position + arena_mode.offset(position, other_transform.translation.truncate());
+
+        let (mut core, radius) = self.world_core(position, transform.rotation);
+        let (other_core, other_radius) = other.world_core(other_position, other_transform.rotation);
+
+        if displacement != Vec2::ZERO {
+            let previous: Vec<Vec2> = core.iter().map(|point| *point - displacement).collect();
+            core.extend(previous);
+            core = convex_hull(core);
+        }
+
+        core_distance(&core, &other_core) <= radius + other_radius
+    }
+
+    /// Every shape is a convex core, a point, a line or a polygon, grown by a radius.
+    fn world_core(&self, position: Vec2, rotation: Quat) -> (Vec<Vec2>, f32) {
+        let to_world = |point: Vec2| position + rotation.mul_vec3(point.extend(0.0)).truncate();
+
+        match &self.shape {
+            ColliderShape::Circle { radius } => (vec![position], *radius),
+            ColliderShape::Capsule { half_length, radius } => (
+                vec![to_world(Vec2::new(0.0, -half_length)), to_world(Vec2::new(0.0, *half_length))],
+                *radius
+            ),
+            ColliderShape::Polygon(points) => (points.iter().copied().map(to_world).collect(), 0.0),
+        }
+    }
+}
+
+/// Distance between two convex cores, 0.0 if they overlap.
+fn core_distance(a: &[Vec2], b: &[Vec2]) -> f32 {
+
+    // When no edges are close, one core can still lie completely inside the other.
+    if (a.len() > 2 && contains(a, b[0])) || (b.len() > 2 && contains(b, a[0])) {
+        return 0.0;
+    }
+
+    edges(a)
+        .flat_map(|(start_a, end_a)| edges(b).map(move |(start_b, end_b)| segment_distance(start_a, end_a, start_b, end_b)))
+        .fold(f32::MAX, f32::min)
+}
+
+fn edges(points: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + Clone + '_ {
+    let count = match points.len() {
+        1 => 1,
+        2 => 1,
+        len => len,
+    };
+
+    (0..count).map(move |index| (points[index], points[(index + 1) % points.len()]))
+}
+
+fn contains(polygon: &[Vec2], point: Vec2) -> bool {
+    edges(polygon).all(|(start, end)| cross(end - start, point - start) >= 0.0)
+}
+
+/// Shortest distance between the segments `p1`-`q1` and `p2`-`q2`.
+fn segment_distance(p1: Vec2, q1: Vec2, p2: Vec2, q2: Vec2) -> f32 {
+
+    let d1 = q1 - p1;
+    let d2 = q2 - p2;
+    let r = p1 - p2;
+    let a = d1.length_squared();
+    let e = d2.length_squared();
+    let f = d2.dot(r);
+
+    let (s, t) = if a <= f32::EPSILON && e <= f32::EPSILON {
+        (0.0, 0.0)
+    } else if a <= f32::EPSILON {
+        (0.0, (f / e).clamp(0.0, 1.0))
+    } else {
+        let c = d1.dot(r);
+
+        if e <= f32::EPSILON {
+            ((-c / a).clamp(0.0, 1.0), 0.0)
+        } else {
+            let b = d1.dot(d2);
+            let denominator = a * e - b * b;
+
+            // Parallel segments, any point of the first one will do as a start.
+            let s = if denominator > f32::EPSILON { ((b * f - c * e) / denominator).clamp(0.0, 1.0) } else { 0.0 };
+            let t = (b * s + f) / e;
+
+            if t < 0.0 {
+                ((-c / a).clamp(0.0, 1.0), 0.0)
+            } else if t > 1.0 {
+                (((b - c) / a).clamp(0.0, 1.0), 1.0)
+            } else {
+                (s, t)
+            }
+        }
+    };
+
+    ((p1 + d1 * s) - (p2 + d2 * t)).length()
+}
+
+/// Counterclockwise convex hull, collinear points collapse into a line and duplicates into a point.
+fn convex_hull(mut points: Vec<Vec2>) -> Vec<Vec2> {
+
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+
+    if points.len() < 3 {
+        return points;
+    }
+
+    let mut lower: Vec<Vec2> = Vec::new();
+    for point in points.iter().copied() {
+        while lower.len() >= 2 && cross(lower[lower.len() - 1] - lower[lower.len() - 2], point - lower[lower.len() - 2]) <= 0.0 {
+            lower.pop();
+        }
+        lower.push(point);
+    }
+
+    let mut upper: Vec<Vec2> = Vec::new();
+    for point in points.iter().rev().copied() {
+        while upper.len() >= 2 && cross(upper[upper.len() - 1] - upper[upper.len() - 2], point - upper[upper.len() - 2]) <= 0.0 {
+            upper.pop();
+        }
+        upper.push(point);
+    }
+
+    lower.pop();
+    upper.pop();
+    lower.extend(upper);
+
+    lower
+}
+
+fn cross(a: Vec2, b: Vec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn at(x: f32, y: f32) -> Transform {
+        Transform::from_xyz(x, y, 0.0)
+    }
+
+    fn square(half: f32) -> Collider {
+        Collider::polygon(vec![
+            Vec2::new(-half, -half),
+            Vec2::new(half, -half),
+            Vec2::new(half, half),
+            Vec2::new(-half, half),
+        ])
+    }
+
+    #[test]
+    fn circles_overlap_by_radius() {
+        let a = Collider::circle(10.0);
+        let b = Collider::circle(5.0);
+
+        assert!(a.overlaps(&at(0.0, 0.0), &b, &at(14.0, 0.0), &ArenaMode::Open));
+        assert!(!a.overlaps(&at(0.0, 0.0), &b, &at(16.0, 0.0), &ArenaMode::Open));
+    }
+
+    #[test]
+    fn capsule_follows_rotation() {
+        let capsule = Collider::capsule(10.0, 1.0);
+        let circle = Collider::circle(2.0);
+        let target = at(0.0, 11.0);
+
+        assert!(capsule.overlaps(&at(0.0, 0.0), &circle, &target, &ArenaMode::Open));
+
+        let rotated = Transform::from_rotation(Quat::from_rotation_z(FRAC_PI_2));
+        assert!(!capsule.overlaps(&rotated, &circle, &target, &ArenaMode::Open));
+        assert!(capsule.overlaps(&rotated, &circle, &at(11.0, 0.0), &ArenaMode::Open));
+    }
+
+    #[test]
+    fn rotated_polygon_reaches_further_along_its_diagonal() {
+        let polygon = square(10.0);
+        let circle = Collider::circle(2.0);
+        let target = at(13.0, 0.0);
+
+        assert!(!polygon.overlaps(&at(0.0, 0.0), &circle, &target, &ArenaMode::Open));
+
+        let rotated = Transform::from_rotation(Quat::from_rotation_z(FRAC_PI_2 / 2.0));
+        assert!(polygon.overlaps(&rotated, &circle, &target, &ArenaMode::Open));
+    }
+
+    #[test]
+    fn polygons_overlap_when_one_contains_the_other() {
+        let large = square(20.0);
+        let small = square(2.0);
+
+        assert!(large.overlaps(&at(0.0, 0.0), &small, &at(5.0, 5.0), &ArenaMode::Open));
+        assert!(small.overlaps(&at(5.0, 5.0), &large, &at(0.0, 0.0), &ArenaMode::Open));
+        assert!(!small.overlaps(&at(30.0, 0.0), &large, &at(0.0, 0.0), &ArenaMode::Open));
+    }
+
+    #[test]
+    fn swept_laser_cannot_tunnel_through_small_rock() {
+        let laser = Collider::capsule(4.0, 1.0);
+        let rock = Collider::circle(8.0);
+        let rock_transform = at(0.0, 50.0);
+        let laser_transform = at(0.0, 100.0);
+        let displacement = Vec2::new(0.0, 80.0);
+
+        assert!(!laser.overlaps(&laser_transform, &rock, &rock_transform, &ArenaMode::Open));
+        assert!(laser.swept_overlaps(&laser_transform, displacement, &rock, &rock_transform, &ArenaMode::Open));
+        assert!(!laser.swept_overlaps(&laser_transform, displacement, &rock, &at(20.0, 50.0), &ArenaMode::Open));
+    }
+
+    #[test]
+    fn overlap_works_across_the_wrap_seam() {
+        let a = Collider::circle(10.0);
+        let b = Collider::circle(10.0);
+
+        assert!(a.overlaps(&at(315.0, 0.0), &b, &at(-315.0, 0.0), &ArenaMode::Wrap));
+        assert!(!a.overlaps(&at(315.0, 0.0), &b, &at(-315.0, 0.0), &ArenaMode::Open));
+    }
+
+    #[test]
+    fn convex_hull_drops_inner_and_collinear_points() {
+        let hull = convex_hull(vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(0.0, 2.0),
+        ]);
+        assert_eq!(hull, vec![Vec2::new(0.0, 0.0), Vec2::new(2.0, 0.0), Vec2::new(2.0, 2.0), Vec2::new(0.0, 2.0)]);
+
+        let line = convex_hull(vec![Vec2::ZERO, Vec2::new(0.0, 1.0), Vec2::new(0.0, 3.0)]);
+        assert_eq!(line, vec![Vec2::ZERO, Vec2::new(0.0, 3.0)]);
+    }
+}
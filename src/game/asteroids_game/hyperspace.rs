@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+use std::time::Duration;
+
+use super::arena::{ArenaMode, ARENA_SIZE};
+use super::player::{self, ActiveShip, MatchMode, Player, PlayerCamera, PlayerLives};
+use super::powerups::ActivePowerUps;
+use super::replay::TickInputs;
+use super::rng::GameRng;
+use super::simulation::FixedTime;
+
+const HYPERSPACE_COOLDOWN: Duration = Duration::from_secs(8);
+const HYPERSPACE_DELAY: Duration = Duration::from_millis(600);
+const HYPERSPACE_RANGE: f32 = 150.0;
+const SELF_DESTRUCT_CHANCE: f64 = 0.1;
+
+/// The ship's panic button, ready whenever the cooldown has finished.
+#[derive(Component)]
+pub struct Hyperspace {
+    cooldown: Timer,
+}
+
+impl Default for Hyperspace {
+    fn default() -> Self {
+        let mut cooldown = Timer::new(HYPERSPACE_COOLDOWN, false);
+        cooldown.tick(HYPERSPACE_COOLDOWN);
+
+        Hyperspace { cooldown }
+    }
+}
+
+impl Hyperspace {
+    pub fn remaining_cooldown(&self) -> Duration {
+        self.cooldown.duration() - self.cooldown.elapsed()
+    }
+}
+
+/// Marks a ship that vanished into hyperspace and reappears at `destination`.
+#[derive(Component)]
+pub struct InHyperspace {
+    delay: Timer,
+    destination: Vec3,
+}
+
+pub fn hyperspace_jump(
+    mut commands: Commands,
+    mut query: Query<(Entity, &Player, &Transform, &mut Hyperspace, &mut Visibility), ActiveShip>,
+    inputs: Res<TickInputs>,
+    arena_mode: Res<ArenaMode>,
+    mut rng: ResMut<GameRng>,
+    time: Res<FixedTime>
+) {
+
+    let mut ships: Vec<_> = query.iter_mut().collect();
+    // Jumps draw from `rng`, so they happen in the same order every run.
+    ships.sort_unstable_by_key(|(_, player, _, _, _)| player.index());
+
+    for (entity, player, transform, mut hyperspace, mut visibility) in ships {
+        hyperspace.cooldown.tick(time.delta());
+
+        if !inputs.get(player.index()).hyperspace() || !hyperspace.cooldown.finished() {
+            continue;
+        }
+
+        jump(&mut commands, entity, transform, &mut hyperspace, &mut visibility, &arena_mode, &mut rng);
+    }
+}
+
+fn jump(
+    commands: &mut Commands,
+    entity: Entity,
+    transform: &Transform,
+    hyperspace: &mut Hyperspace,
+    visibility: &mut Visibility,
+    arena_mode: &ArenaMode,
+    rng: &mut GameRng
+) {
+
+    let destination = match *arena_mode {
+        ArenaMode::Open => transform.translation + Vec3::new(
+            rng.gen_range(-HYPERSPACE_RANGE..HYPERSPACE_RANGE),
+            rng.gen_range(-HYPERSPACE_RANGE..HYPERSPACE_RANGE),
+            0.0
+        ),
+        ArenaMode::Wrap => Vec3::new(
+            rng.gen_range(-ARENA_SIZE.x / 2.0..ARENA_SIZE.x / 2.0),
+            rng.gen_range(-ARENA_SIZE.y / 2.0..ARENA_SIZE.y / 2.0),
+            transform.translation.z
+        ),
+    };
+
+    hyperspace.cooldown.reset();
+    visibility.is_visible = false;
+
+    commands
+        .entity(entity)
+        .insert(InHyperspace {
+            delay: Timer::new(HYPERSPACE_DELAY, false),
+            destination,
+        });
+}
+
+pub fn hyperspace_reappear(
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &Player, &mut Transform, &mut InHyperspace, &mut Visibility, &mut ActivePowerUps, &mut PlayerLives)>,
+    mut camera_query: Query<&mut Transform, (With<PlayerCamera>, Without<Player>)>,
+    arena_mode: Res<ArenaMode>,
+    match_mode: Res<MatchMode>,
+    mut rng: ResMut<GameRng>,
+    time: Res<FixedTime>
+) {
+
+    let mut ships: Vec<_> = player_query.iter_mut().collect();
+    ships.sort_unstable_by_key(|(_, player, _, _, _, _, _)| player.index());
+
+    for (entity, _, mut transform, mut in_hyperspace, mut visibility, mut power_ups, mut player_lives) in ships {
+        in_hyperspace.delay.tick(time.delta());
+
+        if !in_hyperspace.delay.finished() {
+            continue;
+        }
+
+        transform.translation = in_hyperspace.destination;
+        visibility.is_visible = true;
+        commands.entity(entity).remove::<InHyperspace>();
+
+        // Cut the camera straight to the new position instead of letting it chase the ship,
+        // with more ships it keeps framing all of them.
+        if *arena_mode == ArenaMode::Open && *match_mode == MatchMode::Solo {
+            camera_query.for_each_mut(|mut camera_transform| {
+                camera_transform.translation.x = transform.translation.x;
+                camera_transform.translation.y = transform.translation.y;
+            });
+        }
+
+        if rng.gen_bool(SELF_DESTRUCT_CHANCE) {
+            player::destroy_player(&mut commands, entity, transform.translation, &mut visibility, &mut power_ups, &mut player_lives);
+        }
+    }
+}
@@ -1,20 +1,86 @@
 use bevy::prelude::*;
+use bevy::core::FixedTimestep;
+use bevy::input::InputSystem;
+use bevy::transform::TransformSystem;
+use bevy::ui::UiSystem;
 use crate::game::GameState;
 use crate::game::pause::handle_start_pause;
+use simulation::SimulationStage;
 
+mod arena;
 mod asteroids;
 mod background;
+mod collision;
+mod hyperspace;
+mod particles;
 mod player;
+mod powerups;
+mod replay;
+mod rng;
+mod saucers;
+mod simulation;
+mod spatial_hash;
 mod ui;
+mod versus;
+mod waves;
+
+pub(in crate::game) use arena::ArenaMode;
+pub(in crate::game) use player::MatchMode;
+pub(in crate::game) use replay::{list_replays, Replay, ReplaySelection};
+pub(in crate::game) use ui::RadarRange;
 
 pub struct AsteroidsPlugin;
 
 impl Plugin for AsteroidsPlugin {
     fn build(&self, app: &mut App) {
         app
+            .insert_resource(ArenaMode::Open)
+            .insert_resource(MatchMode::Solo)
+            .insert_resource(rng::SeedOverride::from_args())
+            .init_resource::<simulation::FixedTime>()
+            .init_resource::<simulation::LatchedActions>()
+            .init_resource::<replay::ReplaySelection>()
+            .init_resource::<replay::TickInputs>()
+            .insert_resource(ui::RadarRange::load())
+            .add_stage_before(
+                CoreStage::Update,
+                SimulationStage::FixedUpdate,
+                // Single threaded so the systems draw from `GameRng` in the same order every run.
+                Schedule::default()
+                    .with_run_criteria(FixedTimestep::step(simulation::TIMESTEP).with_label(simulation::TIMESTEP_LABEL))
+                    .with_stage(SimulationStage::Begin, SystemStage::single_threaded())
+                    .with_stage(SimulationStage::Update, SystemStage::single_threaded())
+                    .with_stage(SimulationStage::End, SystemStage::single_threaded())
+            )
+            .add_system_to_stage(CoreStage::PreUpdate, simulation::latch_actions.after(InputSystem))
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                simulation::interpolate_transforms.before(TransformSystem::TransformPropagate)
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                background::scroll_starfield
+                    .after(simulation::interpolate_transforms)
+                    .before(TransformSystem::TransformPropagate)
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                ui::update_threat_arrows
+                    .after(simulation::interpolate_transforms)
+                    .before(UiSystem::Flex)
+            )
             .add_system_set(
                 SystemSet::on_enter(GameState::Asteroids)
+                    .with_system(rng::rng_setup
+                                 .before(replay::replay_setup)
+                    )
+                    .with_system(replay::replay_setup)
                     .with_system(asteroids::asteroids_setup)
+                    .with_system(waves::waves_setup)
+                    .with_system(saucers::saucers_setup)
+                    .with_system(versus::versus_setup)
+                    .with_system(particles::particles_setup)
+                    .with_system(spatial_hash::spatial_hash_setup)
                     .with_system(player::spawn_player)
                     .with_system(background::spawn_background)
                     .with_system(ui::spawn_ui)
@@ -22,38 +88,154 @@ impl Plugin for AsteroidsPlugin {
             .add_system_set(
                 SystemSet::on_exit(GameState::Asteroids)
                     .with_system(ui::remove_ui)
+                    .with_system(background::remove_background)
                     .with_system(player::remove_player)
                     .with_system(asteroids::remove_asteroids_atlas)
+                    .with_system(waves::remove_waves)
+                    .with_system(saucers::remove_saucers)
+                    .with_system(versus::remove_versus)
+                    .with_system(powerups::remove_powerups)
+                    .with_system(particles::remove_particles)
+                    .with_system(spatial_hash::remove_spatial_hash)
+                    .with_system(rng::remove_rng)
+                    .with_system(replay::save_replay)
                     .with_system(asteroids::remove_asteroids
                                  .before(asteroids::remove_asteroids_atlas)
                     )
             )
-            .add_system_set(
-                SystemSet::on_update(GameState::Asteroids)
-                    .with_system(player::acceleration)
-                    .with_system(player::rotation
-                                 .after(player::acceleration)
-                    )
-                    .with_system(player::camera_follow
-                                 .after(player::acceleration)
+            .stage(SimulationStage::FixedUpdate, |schedule: &mut Schedule| {
+                schedule
+                    .add_system_to_stage(SimulationStage::Begin, simulation::begin_step)
+                    .add_system_to_stage(
+                        SimulationStage::Begin,
+                        replay::read_input
+                            .with_run_criteria(simulation::run_if_playing)
+                            .after(simulation::begin_step)
                     )
-                    .with_system(player::player_shoot_laser
-                                 .after(player::rotation)
+                    .add_system_to_stage(
+                        SimulationStage::Begin,
+                        versus::hold_ships
+                            .with_run_criteria(simulation::run_if_playing)
+                            .after(replay::read_input)
                     )
-                    .with_system(player::laser_movement)
-                    .with_system(player::laser_collision
-                                 .after(player::laser_movement)
+                    .add_system_to_stage(SimulationStage::End, simulation::end_step)
+                    .add_system_to_stage(SimulationStage::End, player::settle_hits)
+                    .add_system_set_to_stage(
+                        SimulationStage::Update,
+                        SystemSet::new()
+                            .with_run_criteria(simulation::run_if_playing)
+                            .with_system(player::acceleration)
+                            .with_system(player::rotation
+                                         .after(player::acceleration)
+                            )
+                            .with_system(player::camera_follow
+                                         .after(player::acceleration)
+                            )
+                            .with_system(player::player_shoot_laser
+                                         .after(player::rotation)
+                            )
+                            .with_system(player::player_collision
+                                         .after(player::acceleration)
+                            )
+                            .with_system(player::player_respawn
+                                         .after(player::player_collision)
+                            )
+                            .with_system(player::player_invulnerability
+                                         .after(player::player_respawn)
+                            )
+                            .with_system(hyperspace::hyperspace_jump
+                                         .after(player::player_shoot_laser)
+                            )
+                            .with_system(hyperspace::hyperspace_reappear
+                                         .after(hyperspace::hyperspace_jump)
+                                         .after(player::camera_follow)
+                            )
+                            .with_system(player::laser_movement)
+                            .with_system(player::laser_collision
+                                         .after(player::laser_movement)
+                            )
+                            .with_system(player::laser_despawner
+                                         .after(player::laser_collision)
+                            )
+                            .with_system(asteroids::survival_timer)
+                            .with_system(waves::wave_progress
+                                         .after(player::laser_collision)
+                                         .after(player::player_collision)
+                            )
+                            .with_system(asteroids::spawn_asteroid
+                                         .after(waves::wave_progress)
+                            )
+                            .with_system(asteroids::asteroid_rotation)
+                            .with_system(asteroids::asteroid_movement)
+                            .with_system(asteroids::asteroid_collision
+                                         .after(spatial_hash::rebuild_spatial_hash)
+                                         .before(player::laser_collision)
+                                         .before(player::player_collision)
+                            )
+                            .with_system(asteroids::asteroid_distance_cleanup)
+                            .with_system(saucers::spawn_saucer
+                                         .after(waves::wave_progress)
+                            )
+                            .with_system(saucers::saucer_movement)
+                            .with_system(saucers::saucer_shoot
+                                         .after(saucers::saucer_movement)
+                            )
+                            .with_system(saucers::saucer_collision
+                                         .after(spatial_hash::rebuild_spatial_hash)
+                                         .after(player::laser_collision)
+                            )
+                            .with_system(saucers::hostile_laser_collision
+                                         .after(spatial_hash::rebuild_spatial_hash)
+                            )
+                            .with_system(powerups::powerup_movement)
+                            .with_system(powerups::powerup_pickup
+                                         .after(spatial_hash::rebuild_spatial_hash)
+                            )
+                            .with_system(powerups::powerup_effects
+                                         .after(powerups::powerup_pickup)
+                                         .before(player::player_shoot_laser)
+                            )
+                            .with_system(arena::wrap_positions
+                                         .after(player::acceleration)
+                                         .after(player::laser_movement)
+                                         .after(asteroids::asteroid_movement)
+                                         .after(saucers::saucer_movement)
+                                         .after(powerups::powerup_movement)
+                                         .before(player::laser_collision)
+                                         .before(player::player_collision)
+                            )
+                            .with_system(spatial_hash::rebuild_spatial_hash
+                                         .after(arena::wrap_positions)
+                                         .before(player::laser_collision)
+                                         .before(player::player_collision)
+                            )
+                            .with_system(arena::arena_camera)
+                            .with_system(versus::round_progress
+                                         .after(player::laser_collision)
+                                         .after(player::player_collision)
+                                         .after(saucers::hostile_laser_collision)
+                                         .after(hyperspace::hyperspace_reappear)
+                            )
                     )
-                    .with_system(player::laser_despawner
-                                 .after(player::laser_collision)
+            })
+            .add_system_set(
+                SystemSet::on_update(GameState::Asteroids)
+                    .with_system(particles::emit_particles)
+                    .with_system(particles::update_particles
+                                 .after(particles::emit_particles)
                     )
-                    .with_system(asteroids::asteroid_number_timer)
-                    .with_system(asteroids::spawn_asteroid
-                                 .after(asteroids::asteroid_number_timer)
+                    .with_system(ui::update_score)
+                    .with_system(ui::update_lives)
+                    .with_system(ui::update_wave)
+                    .with_system(ui::update_wave_banner)
+                    .with_system(ui::update_powerups)
+                    .with_system(ui::update_hyperspace)
+                    .with_system(ui::update_timer)
+                    .with_system(ui::update_radar)
+                    .with_system(ui::animate_pulse
+                                 .after(ui::update_score)
+                                 .after(ui::update_wave)
                     )
-                    .with_system(asteroids::asteroid_rotation)
-                    .with_system(asteroids::asteroid_movement)
-                    .with_system(asteroids::asteroid_distance_cleanup)
                     .with_system(handle_start_pause)
             );
     }
@@ -0,0 +1,372 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+use std::f32::consts::PI;
+
+use super::player::Player;
+
+const POOL_SIZE: usize = 600;
+const PARTICLE_Z: f32 = 0.8;
+
+/// Describes how the particles of one kind of effect look and move over their life.
+#[derive(Clone, Copy)]
+pub struct ParticleEffect {
+    pub lifetime: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    /// Direction relative to the emitter's rotation, 0.0 points the same way as the emitter.
+    pub direction: f32,
+    /// Width of the cone the particles are emitted in.
+    pub spread: f32,
+    pub start_color: Color,
+    pub end_color: Color,
+    pub start_size: f32,
+    pub end_size: f32,
+}
+
+pub const ENGINE_PLUME: ParticleEffect = ParticleEffect {
+    lifetime: 0.4,
+    min_speed: 40.0,
+    max_speed: 70.0,
+    direction: PI,
+    spread: 0.5,
+    start_color: Color::rgba(1.0, 0.8, 0.3, 1.0),
+    end_color: Color::rgba(1.0, 0.2, 0.0, 0.0),
+    start_size: 4.0,
+    end_size: 1.0,
+};
+
+pub const ASTEROID_DEBRIS: ParticleEffect = ParticleEffect {
+    lifetime: 0.8,
+    min_speed: 20.0,
+    max_speed: 70.0,
+    direction: 0.0,
+    spread: 2.0 * PI,
+    start_color: Color::rgba(0.7, 0.6, 0.5, 1.0),
+    end_color: Color::rgba(0.4, 0.35, 0.3, 0.0),
+    start_size: 4.0,
+    end_size: 2.0,
+};
+
+pub const SHIP_EXPLOSION: ParticleEffect = ParticleEffect {
+    lifetime: 1.2,
+    min_speed: 30.0,
+    max_speed: 100.0,
+    direction: 0.0,
+    spread: 2.0 * PI,
+    start_color: Color::rgba(1.0, 0.9, 0.6, 1.0),
+    end_color: Color::rgba(1.0, 0.3, 0.0, 0.0),
+    start_size: 5.0,
+    end_size: 1.0,
+};
+
+pub const LASER_SPARKS: ParticleEffect = ParticleEffect {
+    lifetime: 0.25,
+    min_speed: 60.0,
+    max_speed: 120.0,
+    direction: 0.0,
+    spread: 2.0 * PI,
+    start_color: Color::rgba(0.6, 1.0, 1.0, 1.0),
+    end_color: Color::rgba(0.2, 0.6, 1.0, 0.0),
+    start_size: 2.0,
+    end_size: 1.0,
+};
+
+/// A pooled particle, inactive particles are hidden and wait in the [`ParticlePool`].
+#[derive(Component, Clone, Copy)]
+pub struct Particle {
+    effect: ParticleEffect,
+    velocity: Vec2,
+    age: f32,
+}
+
+impl Particle {
+    pub fn new(effect: ParticleEffect, velocity: Vec2) -> Self {
+        Particle {
+            effect,
+            velocity,
+            age: 0.0,
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.age < self.effect.lifetime
+    }
+
+    /// Ages the particle and returns how far it moved.
+    pub fn step(&mut self, delta: f32) -> Vec2 {
+        self.age += delta;
+        self.velocity * delta
+    }
+
+    pub fn color(&self) -> Color {
+        let t = self.life_fraction();
+        let start = self.effect.start_color;
+        let end = self.effect.end_color;
+
+        Color::rgba(
+            lerp(start.r(), end.r(), t),
+            lerp(start.g(), end.g(), t),
+            lerp(start.b(), end.b(), t),
+            lerp(start.a(), end.a(), t)
+        )
+    }
+
+    pub fn size(&self) -> f32 {
+        lerp(self.effect.start_size, self.effect.end_size, self.life_fraction())
+    }
+
+    fn life_fraction(&self) -> f32 {
+        (self.age / self.effect.lifetime).clamp(0.0, 1.0)
+    }
+}
+
+impl Default for Particle {
+    fn default() -> Self {
+        // Starts out dead so it goes straight into the pool.
+        Particle {
+            effect: LASER_SPARKS,
+            velocity: Vec2::ZERO,
+            age: LASER_SPARKS.lifetime,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum EmitterMode {
+    Continuous { rate: f32, active: bool, accumulator: f32 },
+    Burst(u32),
+}
+
+#[derive(Component)]
+pub struct ParticleEmitter {
+    effect: ParticleEffect,
+    mode: EmitterMode,
+    offset: Vec2,
+}
+
+impl ParticleEmitter {
+    /// Emits `rate` particles per second at `offset` from the emitter while active.
+    pub fn continuous(effect: ParticleEffect, rate: f32, offset: Vec2) -> Self {
+        ParticleEmitter {
+            effect,
+            mode: EmitterMode::Continuous { rate, active: false, accumulator: 0.0 },
+            offset,
+        }
+    }
+
+    /// Emits `count` particles at once, the emitter entity is despawned afterwards.
+    pub fn burst(effect: ParticleEffect, count: u32) -> Self {
+        ParticleEmitter {
+            effect,
+            mode: EmitterMode::Burst(count),
+            offset: Vec2::ZERO,
+        }
+    }
+
+    pub fn set_active(&mut self, active: bool) {
+        if let EmitterMode::Continuous { active: current, accumulator, .. } = &mut self.mode {
+            if *current != active {
+                *current = active;
+                *accumulator = 0.0;
+            }
+        }
+    }
+
+    /// Number of particles to emit for this frame.
+    pub fn particles_due(&mut self, delta: f32) -> u32 {
+        match &mut self.mode {
+            EmitterMode::Continuous { rate, active, accumulator } => {
+                if !*active {
+                    return 0;
+                }
+
+                *accumulator += *rate * delta;
+                let due = accumulator.floor();
+                *accumulator -= due;
+
+                due as u32
+            },
+            EmitterMode::Burst(count) => *count,
+        }
+    }
+
+    fn is_burst(&self) -> bool {
+        matches!(self.mode, EmitterMode::Burst(_))
+    }
+}
+
+/// Hidden particle entities that are ready to be reused.
+pub struct ParticlePool {
+    free: Vec<Entity>,
+}
+
+pub fn spawn_burst(commands: &mut Commands, effect: ParticleEffect, count: u32, translation: Vec3) {
+    commands
+        .spawn()
+        .insert(Transform::from_translation(translation))
+        .insert(ParticleEmitter::burst(effect, count));
+}
+
+pub fn particles_setup(mut commands: Commands) {
+
+    let free = (0..POOL_SIZE)
+        .map(|_| {
+            commands
+                .spawn_bundle(SpriteBundle {
+                    transform: Transform::from_xyz(0.0, 0.0, PARTICLE_Z),
+                    visibility: Visibility { is_visible: false },
+                    ..Default::default()
+                })
+                .insert(Particle::default())
+                .id()
+        })
+        .collect();
+
+    commands.insert_resource(ParticlePool { free });
+}
+
+pub fn remove_particles(
+    mut commands: Commands,
+    particle_query: Query<Entity, With<Particle>>,
+    emitter_query: Query<(Entity, &ParticleEmitter), Without<Player>>
+) {
+    particle_query.for_each(|entity| {
+        commands.entity(entity).despawn();
+    });
+    emitter_query.for_each(|(entity, emitter)| {
+        if emitter.is_burst() {
+            commands.entity(entity).despawn();
+        }
+    });
+
+    commands.remove_resource::<ParticlePool>();
+}
+
+pub fn emit_particles(
+    mut commands: Commands,
+    mut emitter_query: Query<(Entity, &Transform, &mut ParticleEmitter, Option<&Visibility>), Without<Particle>>,
+    mut particle_query: Query<(&mut Particle, &mut Transform, &mut Visibility)>,
+    mut pool: ResMut<ParticlePool>,
+    time: Res<Time>
+) {
+
+    // Particles are only for show and follow the frame rate, so they stay out of `GameRng`.
+    let mut rng = rand::thread_rng();
+
+    emitter_query.for_each_mut(|(entity, emitter_transform, mut emitter, visibility)| {
+        let due = emitter.particles_due(time.delta_seconds());
+
+        if emitter.is_burst() {
+            commands.entity(entity).despawn();
+        }
+
+        // Hidden emitters, like a destroyed ship, stay quiet.
+        if visibility.is_some_and(|visibility| !visibility.is_visible) {
+            return;
+        }
+
+        let rotation = emitter_transform.rotation.to_euler(EulerRot::ZYX).0;
+        let origin = emitter_transform.translation.truncate() + emitter_transform.rotation.mul_vec3(emitter.offset.extend(0.0)).truncate();
+
+        for _ in 0..due {
+            let pooled = match pool.free.pop() {
+                Some(pooled) => pooled,
+                None => return,
+            };
+
+            if let Ok((mut particle, mut transform, mut particle_visibility)) = particle_query.get_mut(pooled) {
+                let effect = emitter.effect;
+                let angle = rotation + effect.direction + rng.gen_range(-0.5..0.5) * effect.spread;
+                let speed = rng.gen_range(effect.min_speed..effect.max_speed);
+
+                *particle = Particle::new(effect, vec2_from_circle(angle, speed));
+                transform.translation = origin.extend(PARTICLE_Z);
+                particle_visibility.is_visible = true;
+            }
+        }
+    });
+}
+
+pub fn update_particles(
+    mut query: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite, &mut Visibility)>,
+    mut pool: ResMut<ParticlePool>,
+    time: Res<Time>
+) {
+
+    query.for_each_mut(|(entity, mut particle, mut transform, mut sprite, mut visibility)| {
+        if !visibility.is_visible {
+            return;
+        }
+
+        let movement = particle.step(time.delta_seconds());
+
+        if !particle.is_alive() {
+            visibility.is_visible = false;
+            pool.free.push(entity);
+            return;
+        }
+
+        transform.translation.x += movement.x;
+        transform.translation.y += movement.y;
+
+        let size = particle.size();
+        sprite.custom_size = Some(Vec2::new(size, size));
+        sprite.color = particle.color();
+    });
+}
+
+fn lerp(start: f32, end: f32, t: f32) -> f32 {
+    start + (end - start) * t
+}
+
+fn vec2_from_circle(angle: f32, radius: f32) -> Vec2 {
+    Vec2::new(-angle.sin() * radius, angle.cos() * radius)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn particle_moves_and_dies_after_lifetime() {
+        let mut particle = Particle::new(ASTEROID_DEBRIS, Vec2::new(10.0, 0.0));
+
+        let movement = particle.step(0.5);
+        assert_eq!(movement, Vec2::new(5.0, 0.0));
+        assert!(particle.is_alive());
+
+        particle.step(0.5);
+        assert!(!particle.is_alive());
+    }
+
+    #[test]
+    fn particle_interpolates_color_and_size_over_life() {
+        let mut particle = Particle::new(SHIP_EXPLOSION, Vec2::ZERO);
+        assert_eq!(particle.size(), SHIP_EXPLOSION.start_size);
+
+        particle.step(SHIP_EXPLOSION.lifetime / 2.0);
+        let middle = (SHIP_EXPLOSION.start_size + SHIP_EXPLOSION.end_size) / 2.0;
+        assert!((particle.size() - middle).abs() < 1e-5);
+        assert!((particle.color().a() - 0.5).abs() < 1e-5);
+
+        particle.step(SHIP_EXPLOSION.lifetime);
+        assert_eq!(particle.size(), SHIP_EXPLOSION.end_size);
+        assert_eq!(particle.color().a(), 0.0);
+    }
+
+    #[test]
+    fn continuous_emitter_carries_fractions_between_frames() {
+        let mut emitter = ParticleEmitter::continuous(ENGINE_PLUME, 30.0, Vec2::ZERO);
+        assert_eq!(emitter.particles_due(0.1), 0);
+
+        emitter.set_active(true);
+        let emitted: u32 = (0..10).map(|_| emitter.particles_due(0.05)).sum();
+        assert_eq!(emitted, 15);
+    }
+
+    #[test]
+    fn burst_emitter_emits_its_count_at_once() {
+        let mut emitter = ParticleEmitter::burst(LASER_SPARKS, 8);
+        assert_eq!(emitter.particles_due(0.0), 8);
+    }
+}
@@ -1,73 +1,378 @@
 use bevy::prelude::*;
+use bevy::ecs::system::{EntityCommands, SystemParam};
+use bevy::render::camera::CameraProjection;
+use rand::prelude::*;
+use std::f32::consts::{PI, TAU};
+use std::marker::PhantomData;
 use std::time::Duration;
 
-use super::asteroids::{Asteroid, AsteroidsStats};
+use crate::game::GameState;
+use crate::game::game_over::GameResults;
+use super::arena::{ArenaMode, Wrapping, ARENA_SIZE};
+use super::asteroids::{self, Asteroid, AsteroidsAtlas, AsteroidsStats};
+use super::collision::Collider;
+use super::hyperspace::{Hyperspace, InHyperspace};
+use super::particles::{self, ParticleEmitter, ASTEROID_DEBRIS, ENGINE_PLUME, LASER_SPARKS, SHIP_EXPLOSION};
+use super::powerups::{self, ActivePowerUps};
+use super::replay::{TickInput, TickInputs};
+use super::rng::GameRng;
+use super::simulation::{FixedTime, Interpolated};
+use super::spatial_hash::SpatialHash;
+use super::versus::Versus;
 
 const PLAYER_ACCELERATION: f32 = 50.0;
 const PLAYER_DECELERATION: f32 = 0.2;
 const PLAYER_ROT_ACC: f32 = 2.0;
 const PLAYER_ROT_DEC: f32 = 0.5;
+const PLAYER_MAX_ROT: f32 = 2.5;
+/// How fast the ship turns towards the cursor with mouse aim, in radians per second.
+const MOUSE_AIM_TURN_SPEED: f32 = 10.0;
+const PLAYER_START_LIVES: u32 = 3;
+/// Ships in a two player game start this far apart.
+const PLAYER_SPAWN_SPACING: f32 = 80.0;
+const PLAYER_COLORS: [Color; MAX_PLAYERS] = [Color::WHITE, Color::rgb(0.5, 0.8, 1.0)];
+const CAMERA_SCALE: f32 = 0.4;
+/// Room kept around the ships when the camera zooms out to show all of them.
+const CAMERA_MARGIN: f32 = 150.0;
+const CAMERA_ZOOM_SPEED: f32 = 2.0;
+const RESPAWN_DELAY: Duration = Duration::from_secs(2);
+const RESPAWN_SAFE_RADIUS: f32 = 120.0;
+const INVULNERABILITY_TIME: Duration = Duration::from_secs(3);
+const BLINK_INTERVAL: Duration = Duration::from_millis(100);
+const SHIELD_GRACE_TIME: Duration = Duration::from_secs(1);
+const SHIP_SPRITE_SIZE: f32 = 48.0;
+const LASER_HALF_LENGTH: f32 = 6.0;
+const LASER_RADIUS: f32 = 1.5;
+
+pub const MAX_PLAYERS: usize = 2;
+
+/// How many ships take part in a run, picked in the menu before it starts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchMode {
+    Solo,
+    /// Two ships on the same screen, clearing the waves together.
+    Coop,
+    /// Two ships shooting at each other in the classic arena, see [`Versus`].
+    Versus,
+}
+
+impl MatchMode {
+    pub fn next(&self) -> MatchMode {
+        match *self {
+            MatchMode::Solo => MatchMode::Coop,
+            MatchMode::Coop => MatchMode::Versus,
+            MatchMode::Versus => MatchMode::Solo,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            MatchMode::Solo => "Solo",
+            MatchMode::Coop => "Co-op",
+            MatchMode::Versus => "Versus",
+        }
+    }
+
+    pub fn player_count(&self) -> usize {
+        match *self {
+            MatchMode::Solo => 1,
+            MatchMode::Coop | MatchMode::Versus => 2,
+        }
+    }
+}
 
 #[derive(Component, Clone, Copy)]
 pub struct Player {
+    /// Which player flies the ship, it picks their controls, color and score.
+    index: usize,
     velocity: Vec2,
     rotation: f32,
 }
 
+impl Player {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn color(&self) -> Color {
+        player_color(self.index)
+    }
+
+    pub fn velocity(&self) -> Vec2 {
+        self.velocity
+    }
+}
+
+pub fn player_color(index: usize) -> Color {
+    PLAYER_COLORS[index]
+}
+
 #[derive(Component, Clone, Copy)]
 pub struct PlayerCamera;
 
+/// Where the mouse points in the arena, for flying with [`ControlScheme::MouseAim`](crate::game::actions::ControlScheme).
+#[derive(SystemParam)]
+pub struct MouseAim<'w, 's> {
+    windows: Res<'w, Windows>,
+    mouse_buttons: Res<'w, Input<MouseButton>>,
+    camera_query: Query<'w, 's, (&'static GlobalTransform, &'static OrthographicProjection), With<PlayerCamera>>,
+    player_query: Query<'w, 's, (&'static Transform, &'static Player)>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> MouseAim<'w, 's> {
+    /// Heading from the first player's ship to the cursor, `None` while the cursor is outside the window.
+    pub fn heading(&self) -> Option<f32> {
+        let window = self.windows.get_primary()?;
+        let cursor = window.cursor_position()?;
+        let (camera_transform, projection) = self.camera_query.get_single().ok()?;
+        let (player_transform, _) = self.player_query.iter().find(|(_, player)| player.index == 0)?;
+
+        let window_size = Vec2::new(window.width(), window.height());
+        let ndc = (cursor / window_size) * 2.0 - Vec2::ONE;
+        let ndc_to_world = camera_transform.compute_matrix() * projection.get_projection_matrix().inverse();
+        let target = ndc_to_world.project_point3(ndc.extend(-1.0)).truncate();
+
+        let offset = target - player_transform.translation.truncate();
+        if offset == Vec2::ZERO {
+            return None;
+        }
+
+        // The inverse of `vec2_from_circle`.
+        Some((-offset.x).atan2(offset.y))
+    }
+
+    /// Where the first ship faces now, held while the cursor is outside the window.
+    pub fn facing(&self) -> Option<f32> {
+        let (player_transform, _) = self.player_query.iter().find(|(_, player)| player.index == 0)?;
+        Some(player_transform.rotation.to_euler(EulerRot::ZYX).0)
+    }
+
+    pub fn fire(&self) -> bool {
+        self.mouse_buttons.pressed(MouseButton::Left)
+    }
+}
+
+/// Marks a destroyed ship that waits for the area around it to clear before it respawns.
+#[derive(Component)]
+pub struct PlayerRespawn {
+    delay: Timer,
+}
+
+#[derive(Component)]
+pub struct Invulnerable {
+    duration: Timer,
+    blink: Timer,
+}
+
+impl Invulnerable {
+    pub fn new(duration: Duration) -> Self {
+        Invulnerable {
+            duration: Timer::new(duration, false),
+            blink: Timer::new(BLINK_INTERVAL, true),
+        }
+    }
+}
+
+/// Query filter for ships flying in the arena, neither waiting to respawn nor in hyperspace.
+pub type ActiveShip = (Without<PlayerRespawn>, Without<InHyperspace>);
+/// Query filter for flying ships that can currently be destroyed.
+pub type VulnerableShip = (Without<PlayerRespawn>, Without<InHyperspace>, Without<Invulnerable>);
+
+#[derive(Component)]
+pub struct PlayerLives {
+    lives: u32,
+    /// Set as soon as the ship goes down, the `PlayerRespawn` marking it is only added at the end
+    /// of the stage, after every collision system has had its go at the ship.
+    destroyed: bool,
+    /// Set when a shield takes a hit, until the grace period it grants is added at the end of the stage.
+    shield_hit: bool,
+}
+
+impl PlayerLives {
+    pub fn lives(&self) -> u32 {
+        self.lives
+    }
+
+    /// Whether the ship went down and has not come back yet.
+    pub fn destroyed(&self) -> bool {
+        self.destroyed
+    }
+
+    pub fn gain_life(&mut self) {
+        self.lives += 1;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FirePattern {
+    Single,
+    /// Several bolts fanned out evenly over `angle` radians.
+    Spread { bolts: u32, angle: f32 },
+}
+
+impl FirePattern {
+    /// Rotation of every bolt relative to the direction the ship is facing.
+    pub fn angles(&self) -> Vec<f32> {
+        match *self {
+            FirePattern::Single => vec![0.0],
+            FirePattern::Spread { bolts, angle } => {
+                let step = angle / (bolts.max(2) - 1) as f32;
+
+                (0..bolts)
+                    .map(|bolt| bolt as f32 * step - angle / 2.0)
+                    .collect()
+            },
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct LaserShooter {
     cooldown: Timer,
     offset: f32,
+    pattern: FirePattern,
 }
 
 impl LaserShooter {
     pub const MAX_COOLDOWN: Duration = Duration::from_millis(200);
     pub const SPEED: f32 = 200.0;
     pub const LIFETIME: Duration = Duration::from_secs(5);
+
+    pub fn set_pattern(&mut self, pattern: FirePattern) {
+        self.pattern = pattern;
+    }
+
+    pub fn set_cooldown(&mut self, cooldown: Duration) {
+        if self.cooldown.duration() != cooldown {
+            self.cooldown.set_duration(cooldown);
+        }
+    }
 }
 
 #[derive(Component)]
 pub struct LaserBullet {
     velocity: Vec2,
     life_time: Timer,
+    /// The player who fired it, hostile lasers have none.
+    shooter: Option<usize>,
+    /// Set as soon as the laser hits something, it is only despawned at the end of the stage.
+    spent: bool,
+}
+
+impl LaserBullet {
+    pub fn velocity(&self) -> Vec2 {
+        self.velocity
+    }
+
+    pub fn shooter(&self) -> Option<usize> {
+        self.shooter
+    }
+
+    pub fn spent(&self) -> bool {
+        self.spent
+    }
+}
+
+/// Marks lasers fired by enemies, they only damage the player.
+#[derive(Component)]
+pub struct Hostile;
+
+/// Where one of the ships still in the game is, things are spawned around it. Only a game with
+/// several of them draws from `rng` to pick one.
+pub fn random_player_translation(query: &Query<(&Transform, &Player, &PlayerLives)>, rng: &mut GameRng) -> Vec3 {
+    let mut players: Vec<(&Transform, &Player)> = query
+        .iter()
+        .filter(|(_, _, player_lives)| player_lives.lives > 0)
+        .map(|(transform, player, _)| (transform, player))
+        .collect();
+    // Sorted, the ships may be stored in any order.
+    players.sort_unstable_by_key(|(_, player)| player.index);
+
+    let index = if players.len() > 1 { rng.gen_range(0..players.len()) } else { 0 };
+    players.get(index).map_or(Vec3::ZERO, |(transform, _)| transform.translation)
+}
+
+/// Triangle around the visible part of a ship sprite that is `sprite_size` wide, pointing up.
+pub fn ship_collider(sprite_size: f32) -> Collider {
+    let scale = sprite_size / SHIP_SPRITE_SIZE;
+
+    Collider::polygon(vec![
+        Vec2::new(0.0, 18.0) * scale,
+        Vec2::new(-14.0, -14.0) * scale,
+        Vec2::new(14.0, -14.0) * scale,
+    ])
+}
+
+/// Where a ship starts. Together the ships start side by side, in versus on opposite sides
+/// of the arena facing each other.
+pub fn start_transform(match_mode: MatchMode, index: usize) -> Transform {
+    let player_count = match_mode.player_count();
+
+    match match_mode {
+        MatchMode::Solo | MatchMode::Coop => {
+            let x = (index as f32 - (player_count - 1) as f32 / 2.0) * PLAYER_SPAWN_SPACING;
+            Transform::from_xyz(x, 0.0, 1.0)
+        },
+        MatchMode::Versus => {
+            let side = if index == 0 { -1.0 } else { 1.0 };
+            Transform::from_xyz(side * ARENA_SIZE.x / 4.0, 0.0, 1.0).with_rotation(Quat::from_rotation_z(side * PI / 2.0))
+        },
+    }
 }
 
-pub fn spawn_player(mut commands: Commands, asset_server: Res<AssetServer>) {
+pub fn spawn_player(mut commands: Commands, asset_server: Res<AssetServer>, match_mode: Res<MatchMode>) {
 
     let mut ortho_camera = OrthographicCameraBundle::new_2d();
-    ortho_camera.orthographic_projection.scale = 0.4;
+    ortho_camera.orthographic_projection.scale = CAMERA_SCALE;
+    let camera_transform = ortho_camera.transform;
 
     commands
         .spawn_bundle(ortho_camera)
-            .insert(PlayerCamera);
+            .insert(PlayerCamera)
+            .insert(Interpolated::new(camera_transform));
 
     let ship_handle = asset_server.load("images/ship.png");
 
-    commands
-        .spawn_bundle(SpriteBundle {
-            sprite: Sprite {
-                custom_size: Some(Vec2::new(48.0, 48.0)),
-                anchor: bevy::sprite::Anchor::Center,
-                ..Default::default()
-            },
-            texture: ship_handle,
-            transform: Transform {
-                translation: Vec3::new(0.0, 0.0, 1.0),
+    for index in 0..match_mode.player_count() {
+        let transform = start_transform(*match_mode, index);
+
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: player_color(index),
+                    custom_size: Some(Vec2::new(SHIP_SPRITE_SIZE, SHIP_SPRITE_SIZE)),
+                    anchor: bevy::sprite::Anchor::Center,
+                    ..Default::default()
+                },
+                texture: ship_handle.clone(),
+                transform,
                 ..Default::default()
-            },
-            ..Default::default()
-        })
-        .insert(Player {
-            velocity: Vec2::ZERO,
-            rotation: 0.0,
-        })
-        .insert(LaserShooter {
-            cooldown: Timer::new(LaserShooter::MAX_COOLDOWN, false),
-            offset: 30.0,
-        });
+            })
+            .insert(Player {
+                index,
+                velocity: Vec2::ZERO,
+                rotation: 0.0,
+            })
+            .insert(PlayerLives {
+                lives: PLAYER_START_LIVES,
+                destroyed: false,
+                shield_hit: false,
+            })
+            .insert(LaserShooter {
+                cooldown: Timer::new(LaserShooter::MAX_COOLDOWN, false),
+                offset: 30.0,
+                pattern: FirePattern::Single,
+            })
+            .insert(Wrapping)
+            .insert(Interpolated::new(transform))
+            .insert(ship_collider(SHIP_SPRITE_SIZE))
+            .insert(ActivePowerUps::default())
+            .insert(Hyperspace::default())
+            .insert(ParticleEmitter::continuous(ENGINE_PLUME, 60.0, Vec2::new(0.0, -20.0)))
+            .insert(Invulnerable::new(INVULNERABILITY_TIME));
+    }
 }
 
 pub fn remove_player(
@@ -75,34 +380,44 @@ pub fn remove_player(
     player_query: Query<Entity, With<Player>>,
     player_camera_query: Query<Entity, With<PlayerCamera>>,
 ) {
-    commands.entity(player_query.single()).despawn();
+    player_query.for_each(|entity| {
+        commands.entity(entity).despawn();
+    });
     commands.entity(player_camera_query.single()).despawn();
 }
 
 pub fn rotation(
-    mut query: Query<(&mut Transform, &mut Player)>,
-    keys: Res<Input<KeyCode>>,
-    time: Res<Time>
+    mut query: Query<(&mut Transform, &mut Player), ActiveShip>,
+    inputs: Res<TickInputs>,
+    time: Res<FixedTime>
 ) {
+    query.for_each_mut(|(transform, player)| {
+        let input = inputs.get(player.index);
+        rotate_ship(transform, player, input, &time);
+    });
+}
 
-    let (mut transform, mut player) = query.single_mut();
-    let mut rotated = false;
+fn rotate_ship(mut transform: Mut<Transform>, mut player: Mut<Player>, input: TickInput, time: &FixedTime) {
 
-    if keys.pressed(KeyCode::D) {
-        if player.rotation > -2.5 {
-            player.rotation -= PLAYER_ROT_ACC * time.delta_seconds();
-        }
-        rotated = true;
-    }
+    if let Some(heading) = input.aim() {
+        // Turn the shorter way round, without overshooting the cursor.
+        let facing = transform.rotation.to_euler(EulerRot::ZYX).0;
+        let difference = (heading - facing + PI).rem_euclid(TAU) - PI;
+        let turn = difference.clamp(-MOUSE_AIM_TURN_SPEED * time.delta_seconds(), MOUSE_AIM_TURN_SPEED * time.delta_seconds());
 
-    if keys.pressed(KeyCode::A) {
-        if player.rotation < 2.5 {
-            player.rotation += PLAYER_ROT_ACC * time.delta_seconds();
-        }
-        rotated = true;
+        player.rotation = 0.0;
+        transform.rotation = transform.rotation.mul_quat(Quat::from_rotation_z(turn));
+        return;
     }
 
-    if !rotated {
+    let turn = input.rotation();
+
+    if turn != 0.0 {
+        // A stick pushed part way turns slower and tops out at a lower speed.
+        if player.rotation * turn.signum() < PLAYER_MAX_ROT * turn.abs() {
+            player.rotation += PLAYER_ROT_ACC * turn * time.delta_seconds();
+        }
+    } else {
         let reduction = player.rotation * PLAYER_ROT_DEC * time.delta_seconds();
         player.rotation -= reduction;
     }
@@ -112,29 +427,40 @@ pub fn rotation(
 }
 
 pub fn acceleration(
-    mut query: Query<(&mut Transform, &mut Player)>,
-    keys: Res<Input<KeyCode>>,
-    time: Res<Time>
+    mut query: Query<(&mut Transform, &mut Player, &mut ParticleEmitter), ActiveShip>,
+    inputs: Res<TickInputs>,
+    time: Res<FixedTime>
+) {
+    query.for_each_mut(|(transform, player, engine_plume)| {
+        let input = inputs.get(player.index);
+        accelerate_ship(transform, player, engine_plume, input, &time);
+    });
+}
+
+fn accelerate_ship(
+    mut transform: Mut<Transform>,
+    mut player: Mut<Player>,
+    mut engine_plume: Mut<ParticleEmitter>,
+    input: TickInput,
+    time: &FixedTime
 ) {
-    let (mut transform, mut player) = query.single_mut();
-    let mut accelerated = false;
 
     let rotation = transform.rotation.to_euler(EulerRot::ZYX);
     let direction_vec = vec2_from_circle(rotation.0, 1.0);
 
-    if keys.pressed(KeyCode::W) {
-        let acc = direction_vec * PLAYER_ACCELERATION * time.delta_seconds();
-        player.velocity += acc;
-        accelerated = true;
-    }
+    // Triggers push part way, keys all the way. With mouse aim the push does not depend on
+    // where the ship faces.
+    let push = match input.aim() {
+        Some(_) => input.strafe(),
+        None => direction_vec * (input.thrust() - input.reverse()),
+    };
 
-    if keys.pressed(KeyCode::S) {
-        let acc = direction_vec * PLAYER_ACCELERATION * time.delta_seconds();
-        player.velocity -= acc;
-        accelerated = true;
-    }
+    engine_plume.set_active(input.thrust() > 0.0 || push.dot(direction_vec) > 0.0);
 
-    if !accelerated {
+    if input.thrust() > 0.0 || input.reverse() > 0.0 || input.strafe() != Vec2::ZERO {
+        let acc = push * PLAYER_ACCELERATION * time.delta_seconds();
+        player.velocity += acc;
+    } else {
         let reduction = player.velocity * PLAYER_DECELERATION * time.delta_seconds();
         player.velocity -= reduction;
     }
@@ -143,22 +469,52 @@ pub fn acceleration(
     transform.translation.y += player.velocity.y * time.delta_seconds();
 }
 
+/// Follows the middle of the ships still in the game and zooms out until all of them fit.
+#[allow(clippy::type_complexity)]
 pub fn camera_follow(
-    player_query: Query<&Transform, With<Player>>,
-    mut camera_query: Query<&mut Transform, (With<PlayerCamera>, Without<Player>)>,
-    time: Res<Time>
+    player_query: Query<(&Transform, &PlayerLives, Option<&PlayerRespawn>), With<Player>>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), (With<PlayerCamera>, Without<Player>)>,
+    arena_mode: Res<ArenaMode>,
+    windows: Res<Windows>,
+    time: Res<FixedTime>
 ) {
-    let player_transform = player_query.single();
-    let mut camera_transform = camera_query.single_mut();
 
-    let player_translation = player_transform.translation.truncate();
+    if *arena_mode == ArenaMode::Wrap {
+        return;
+    }
+
+    let (mut camera_transform, mut projection) = camera_query.single_mut();
+
+    // Ships out of lives stay where they were destroyed, they are not followed there.
+    let translations: Vec<Vec2> = player_query
+        .iter()
+        .filter(|(_, player_lives, respawn)| player_lives.lives > 0 || respawn.is_none())
+        .map(|(transform, _, _)| transform.translation.truncate())
+        .collect();
+
+    if translations.is_empty() {
+        return;
+    }
+
+    let min = translations.iter().copied().fold(Vec2::splat(f32::MAX), Vec2::min);
+    let max = translations.iter().copied().fold(Vec2::splat(f32::MIN), Vec2::max);
+
+    if let Some(window) = windows.get_primary() {
+        let spread = max - min + Vec2::splat(CAMERA_MARGIN * 2.0);
+        let scale = CAMERA_SCALE.max(spread.x / window.width()).max(spread.y / window.height());
+
+        projection.scale += (scale - projection.scale) * (CAMERA_ZOOM_SPEED * time.delta_seconds()).min(1.0);
+    }
+
+    let player_translation = (min + max) / 2.0;
     let camera_translation = camera_transform.translation.truncate();
 
     let diff_translation = player_translation - camera_translation;
     let diff_length = diff_translation.length();
 
     let correction_strength = (diff_length * 0.01) * (diff_length * 0.01);
-    let correction = diff_translation * (correction_strength * time.delta().as_secs_f32());
+    // Never move past the ship, otherwise large gaps make the camera overshoot wildly.
+    let correction = diff_translation * (correction_strength * time.delta().as_secs_f32()).min(1.0);
 
     camera_transform.translation.x += correction.x;
     camera_transform.translation.y += correction.y;
@@ -166,91 +522,395 @@ pub fn camera_follow(
 
 pub fn player_shoot_laser(
     mut commands: Commands,
-    mut query: Query<(&Transform, &Player, &mut LaserShooter)>,
-    keys: Res<Input<KeyCode>>,
-    time: Res<Time>,
-    asset_server: Res<AssetServer>
+    mut query: Query<(&Transform, &Player, &mut LaserShooter), ActiveShip>,
+    inputs: Res<TickInputs>,
+    time: Res<FixedTime>,
+    asset_server: Res<AssetServer>,
+    mut asteroid_stats: ResMut<AsteroidsStats>
 ) {
 
-    let (player_transform, player, mut laser_shooter) = query.single_mut();
-    laser_shooter.cooldown.tick(time.delta());
+    query.for_each_mut(|(player_transform, player, mut laser_shooter)| {
+        laser_shooter.cooldown.tick(time.delta());
 
-    if keys.pressed(KeyCode::Space) && laser_shooter.cooldown.finished() {
+        if inputs.get(player.index).fire() && laser_shooter.cooldown.finished() {
 
-        laser_shooter.cooldown.reset();
+            laser_shooter.cooldown.reset();
 
-        let texture_handle = asset_server.load("images/laser.png");
+            let texture_handle = asset_server.load("images/laser.png");
+            let rotation = player_transform.rotation.to_euler(EulerRot::ZYX);
 
-        let rotation = player_transform.rotation.to_euler(EulerRot::ZYX);
-        let direction = vec2_from_circle(rotation.0, 1.0);
-        let velocity = Vec2::new(player.velocity.x + direction.x * LaserShooter::SPEED, player.velocity.y + direction.y * LaserShooter::SPEED);
+            for angle in laser_shooter.pattern.angles() {
+                asteroid_stats.shot_fired();
 
-        let translation = Vec3::new(
-            player_transform.translation.x + direction.x * laser_shooter.offset,
-            player_transform.translation.y + direction.y * laser_shooter.offset,
-            player_transform.translation.z
-        );
+                let direction = vec2_from_circle(rotation.0 + angle, 1.0);
+                let velocity = Vec2::new(player.velocity.x + direction.x * LaserShooter::SPEED, player.velocity.y + direction.y * LaserShooter::SPEED);
 
-        let transform = Transform {
-            translation,
-            rotation: player_transform.rotation,
-            scale: player_transform.scale
-        };
+                let translation = Vec3::new(
+                    player_transform.translation.x + direction.x * laser_shooter.offset,
+                    player_transform.translation.y + direction.y * laser_shooter.offset,
+                    player_transform.translation.z
+                );
 
-        commands
-            .spawn_bundle(SpriteBundle {
-                sprite: Sprite {
-                    custom_size: Some(Vec2::new(24.0, 24.0)),
-                    anchor: bevy::sprite::Anchor::Center,
-                    ..Default::default()
-                },
-                transform,
-                texture: texture_handle,
-                ..Default::default()
-            })
-            .insert(LaserBullet {
-                velocity,
-                life_time: Timer::new(LaserShooter::LIFETIME, false),
-            });
-    }
+                let transform = Transform {
+                    translation,
+                    rotation: player_transform.rotation.mul_quat(Quat::from_rotation_z(angle)),
+                    scale: player_transform.scale
+                };
+
+                spawn_laser(&mut commands, texture_handle.clone(), transform, velocity, Some(player.index));
+            }
+        }
+    });
+}
+
+/// Spawns a laser bolt, shared by the player and the hostile saucers.
+pub fn spawn_laser<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    texture_handle: Handle<Image>,
+    transform: Transform,
+    velocity: Vec2,
+    shooter: Option<usize>
+) -> EntityCommands<'w, 's, 'a> {
 
+    let mut laser = commands.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            custom_size: Some(Vec2::new(24.0, 24.0)),
+            anchor: bevy::sprite::Anchor::Center,
+            ..Default::default()
+        },
+        transform,
+        texture: texture_handle,
+        ..Default::default()
+    });
+
+    laser
+        .insert(LaserBullet {
+            velocity,
+            life_time: Timer::new(LaserShooter::LIFETIME, false),
+            shooter,
+            spent: false,
+        })
+        .insert(Collider::capsule(LASER_HALF_LENGTH, LASER_RADIUS))
+        .insert(Interpolated::new(transform))
+        .insert(Wrapping);
+
+    laser
 }
 
-pub fn laser_movement(mut query: Query<(&mut Transform, &LaserBullet)>, time: Res<Time>) {
+pub fn laser_movement(mut query: Query<(&mut Transform, &LaserBullet)>, time: Res<FixedTime>) {
     query.for_each_mut(|(mut transform, laser_bullet)| {
         transform.translation.x += laser_bullet.velocity.x * time.delta_seconds();
         transform.translation.y += laser_bullet.velocity.y * time.delta_seconds();
     });
 }
 
+/// Breaks the asteroids hit by the players' lasers. In versus the lasers also destroy any
+/// ship other than the one that fired them.
+#[allow(clippy::too_many_arguments)]
 pub fn laser_collision(
     mut commands: Commands,
-    laser_query: Query<(Entity, &Transform), With<LaserBullet>>,
-    asteroid_query: Query<(Entity, &Transform), With<Asteroid>>,
-    mut asteroid_stats: ResMut<AsteroidsStats>
+    mut laser_query: Query<(Entity, &Transform, &mut LaserBullet, &Collider), Without<Hostile>>,
+    mut asteroid_query: Query<(Entity, &Transform, &mut Asteroid, &Collider)>,
+    mut player_query: Query<(&Player, &Transform, &Collider, &mut Visibility, &mut ActivePowerUps, &mut PlayerLives), VulnerableShip>,
+    spatial_hash: Res<SpatialHash>,
+    asteroids_atlas: Res<AsteroidsAtlas>,
+    arena_mode: Res<ArenaMode>,
+    versus: Option<Res<Versus>>,
+    mut asteroid_stats: ResMut<AsteroidsStats>,
+    mut rng: ResMut<GameRng>,
+    time: Res<FixedTime>
 ) {
 
-    if !laser_query.is_empty() && !asteroid_query.is_empty() {
+    let ships_are_targets = versus.is_some_and(|versus| versus.fighting());
+
+    if !laser_query.is_empty() && (ships_are_targets || !asteroid_query.is_empty()) {
 
-        laser_query.for_each(|(laser, laser_transform)| {
-            asteroid_query.for_each(|(asteroid, asteroid_transform)| {
+        let mut hit_ships = Vec::new();
+
+        for (laser, laser_transform, mut laser_bullet, laser_collider) in laser_query.iter_mut() {
+            let displacement = laser_bullet.velocity * time.delta_seconds();
+            let nearby = spatial_hash.nearby_swept(laser_transform.translation.truncate(), laser_collider.bounding_radius(), displacement);
+
+            if ships_are_targets {
+                let target = nearby.iter().copied().find(|entity| {
+                    match player_query.get(*entity) {
+                        Ok((player, player_transform, player_collider, _, _, _)) =>
+                            laser_bullet.shooter != Some(player.index)
+                                && !hit_ships.contains(entity)
+                                && laser_collider.swept_overlaps(laser_transform, displacement, player_collider, player_transform, &arena_mode),
+                        Err(_) => false,
+                    }
+                });
+
+                if let Some(ship) = target {
+                    let (_, player_transform, _, mut visibility, mut power_ups, mut player_lives) = player_query.get_mut(ship).unwrap();
 
-                if laser_transform.translation.distance(asteroid_transform.translation) < 25.0 {
                     commands.entity(laser).despawn();
-                    commands.entity(asteroid).despawn();
+                    laser_bullet.spent = true;
+                    hit_ships.push(ship);
+                    asteroid_stats.shot_hit();
+                    particles::spawn_burst(&mut commands, LASER_SPARKS, 6, laser_transform.translation);
+                    destroy_player(&mut commands, ship, player_transform.translation, &mut visibility, &mut power_ups, &mut player_lives);
+                    continue;
+                }
+            }
 
-                    asteroid_stats.destroyed();
-                    println!("{}", asteroid_stats.destroyed_number());
+            for entity in nearby.iter() {
+                let (asteroid_entity, asteroid_transform, mut asteroid, asteroid_collider) = match asteroid_query.get_mut(*entity) {
+                    Ok(asteroid) => asteroid,
+                    Err(_) => continue,
+                };
+
+                if asteroid.broken() {
+                    continue;
                 }
-            });
+
+                if laser_collider.swept_overlaps(laser_transform, displacement, asteroid_collider, asteroid_transform, &arena_mode) {
+                    commands.entity(laser).despawn();
+                    commands.entity(asteroid_entity).despawn();
+                    laser_bullet.spent = true;
+                    asteroid.mark_broken();
+
+                    asteroid_stats.destroyed(asteroid.size(), laser_bullet.shooter);
+                    asteroid_stats.shot_hit();
+                    powerups::maybe_drop(&mut commands, &mut rng, asteroid_transform.translation);
+                    particles::spawn_burst(&mut commands, LASER_SPARKS, 6, laser_transform.translation);
+                    particles::spawn_burst(&mut commands, ASTEROID_DEBRIS, 10, asteroid_transform.translation);
+                    asteroids::spawn_fragments(
+                        &mut commands,
+                        &asteroids_atlas,
+                        &mut asteroid_stats,
+                        &mut rng,
+                        &asteroid,
+                        asteroid_transform.translation
+                    );
+
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn player_collision(
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &Player, &Transform, &Collider, &mut Visibility, &mut ActivePowerUps, &mut PlayerLives), VulnerableShip>,
+    mut asteroid_query: Query<(Entity, &Transform, &mut Asteroid, &Collider)>,
+    spatial_hash: Res<SpatialHash>,
+    asteroids_atlas: Res<AsteroidsAtlas>,
+    mut asteroid_stats: ResMut<AsteroidsStats>,
+    mut rng: ResMut<GameRng>,
+    arena_mode: Res<ArenaMode>
+) {
+
+    let mut ships: Vec<_> = player_query.iter_mut().collect();
+    // Breaking asteroids draws from `rng`, so the ships are checked in the same order every run.
+    ships.sort_unstable_by_key(|(_, player, _, _, _, _, _)| player.index);
+
+    for (player_entity, player, player_transform, player_collider, mut visibility, mut power_ups, mut player_lives) in ships {
+        let nearby = spatial_hash.nearby(player_transform.translation.truncate(), player_collider.bounding_radius());
+
+        // Two ships may touch the same asteroid, it can only break once.
+        let hit = nearby.iter().copied().find(|entity| {
+            match asteroid_query.get(*entity) {
+                Ok((_, asteroid_transform, asteroid, asteroid_collider)) =>
+                    !asteroid.broken() && player_collider.overlaps(player_transform, asteroid_collider, asteroid_transform, &arena_mode),
+                Err(_) => false,
+            }
+        });
+
+        if let Some(asteroid_entity) = hit {
+            let (_, asteroid_transform, mut asteroid, _) = asteroid_query.get_mut(asteroid_entity).unwrap();
+
+            commands.entity(asteroid_entity).despawn();
+            asteroid.mark_broken();
+            asteroid_stats.destroyed(asteroid.size(), Some(player.index));
+            particles::spawn_burst(&mut commands, ASTEROID_DEBRIS, 10, asteroid_transform.translation);
+            asteroids::spawn_fragments(
+                &mut commands,
+                &asteroids_atlas,
+                &mut asteroid_stats,
+                &mut rng,
+                &asteroid,
+                asteroid_transform.translation
+            );
+
+            destroy_player(&mut commands, player_entity, player_transform.translation, &mut visibility, &mut power_ups, &mut player_lives);
+        }
+    }
+}
+
+/// Hides the ship and costs a life, the ship comes back in [`player_respawn`].
+/// An active shield absorbs the hit instead and leaves the ship briefly invulnerable.
+pub fn destroy_player(
+    commands: &mut Commands,
+    player: Entity,
+    translation: Vec3,
+    visibility: &mut Visibility,
+    power_ups: &mut ActivePowerUps,
+    player_lives: &mut PlayerLives
+) {
+    // Hit twice in the same step, the first hit already decided what happens to the ship.
+    if player_lives.destroyed || player_lives.shield_hit {
+        return;
+    }
+
+    if power_ups.consume_shield() {
+        player_lives.shield_hit = true;
+        commands
+            .entity(player)
+            .insert(Invulnerable::new(SHIELD_GRACE_TIME));
+        return;
+    }
+
+    power_ups.clear();
+    particles::spawn_burst(commands, SHIP_EXPLOSION, 30, translation);
+    player_lives.lives = player_lives.lives.saturating_sub(1);
+    player_lives.destroyed = true;
+    visibility.is_visible = false;
+
+    commands
+        .entity(player)
+        .insert(PlayerRespawn {
+            delay: Timer::new(RESPAWN_DELAY, false),
+        });
+}
+
+/// Brings a ship back to where it started as good as new, protected for `protection`, when a
+/// versus round starts over.
+#[allow(clippy::too_many_arguments)]
+pub fn reset_ship(
+    commands: &mut Commands,
+    entity: Entity,
+    transform: &mut Transform,
+    player: &mut Player,
+    visibility: &mut Visibility,
+    power_ups: &mut ActivePowerUps,
+    player_lives: &mut PlayerLives,
+    match_mode: MatchMode,
+    protection: Duration
+) {
+    *transform = start_transform(match_mode, player.index);
+    player.velocity = Vec2::ZERO;
+    player.rotation = 0.0;
+    player_lives.lives = PLAYER_START_LIVES;
+    player_lives.destroyed = false;
+    visibility.is_visible = true;
+    power_ups.clear();
+
+    commands
+        .entity(entity)
+        .remove::<PlayerRespawn>()
+        .remove::<InHyperspace>()
+        .insert(Hyperspace::default())
+        .insert(Invulnerable::new(protection));
+}
+
+/// Brings destroyed ships back once their area is clear. The game is over when no ship has
+/// any lives left. In versus the rounds bring the ships back instead.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn player_respawn(
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &mut Transform, &mut Player, &mut PlayerLives, Option<&mut PlayerRespawn>, &mut Visibility)>,
+    asteroid_query: Query<&Transform, (With<Asteroid>, Without<Player>)>,
+    asteroid_stats: Res<AsteroidsStats>,
+    arena_mode: Res<ArenaMode>,
+    match_mode: Res<MatchMode>,
+    rng: Res<GameRng>,
+    mut game_state: ResMut<State<GameState>>,
+    time: Res<FixedTime>
+) {
+
+    if *match_mode == MatchMode::Versus {
+        return;
+    }
+
+    let mut out_of_lives = 0;
+
+    for (entity, mut transform, mut player, mut player_lives, respawn, mut visibility) in player_query.iter_mut() {
+        let mut respawn = match respawn {
+            Some(respawn) => respawn,
+            None => continue,
+        };
+
+        respawn.delay.tick(time.delta());
+
+        if !respawn.delay.finished() {
+            continue;
+        }
+
+        if player_lives.lives == 0 {
+            out_of_lives += 1;
+            continue;
+        }
+
+        let spawn_point = transform.translation.truncate();
+        let area_clear = asteroid_query
+            .iter()
+            .all(|asteroid_transform| arena_mode.distance(spawn_point, asteroid_transform.translation.truncate()) > RESPAWN_SAFE_RADIUS);
+
+        if area_clear {
+            player.velocity = Vec2::ZERO;
+            player.rotation = 0.0;
+            transform.rotation = Quat::IDENTITY;
+            visibility.is_visible = true;
+            player_lives.destroyed = false;
+
+            commands
+                .entity(entity)
+                .remove::<PlayerRespawn>()
+                .insert(Invulnerable::new(INVULNERABILITY_TIME));
+        }
+    }
+
+    if out_of_lives == match_mode.player_count() {
+        commands.insert_resource(GameResults {
+            score: asteroid_stats.score(),
+            player_scores: (0..match_mode.player_count()).map(|index| asteroid_stats.player_score(index)).collect(),
+            destroyed: asteroid_stats.destroyed_number(),
+            accuracy: asteroid_stats.accuracy(),
+            time_survived: asteroid_stats.elapsed(),
+            seed: rng.seed(),
+            winner: None,
+            rounds_won: Vec::new(),
         });
+        game_state.overwrite_push(GameState::GameOver).unwrap();
     }
 }
 
+/// Lets shielded ships be hit again once the grace period from the last hit is in place.
+pub fn settle_hits(mut query: Query<&mut PlayerLives>) {
+    query.for_each_mut(|mut player_lives| {
+        if player_lives.shield_hit {
+            player_lives.shield_hit = false;
+        }
+    });
+}
+
+pub fn player_invulnerability(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Invulnerable, &mut Visibility), ActiveShip>,
+    time: Res<FixedTime>
+) {
+
+    query.for_each_mut(|(entity, mut invulnerable, mut visibility)| {
+        invulnerable.duration.tick(time.delta());
+        invulnerable.blink.tick(time.delta());
+
+        if invulnerable.duration.finished() {
+            visibility.is_visible = true;
+            commands.entity(entity).remove::<Invulnerable>();
+        } else if invulnerable.blink.just_finished() {
+            visibility.is_visible = !visibility.is_visible;
+        }
+    });
+}
+
 pub fn laser_despawner(
     mut commands: Commands,
     mut query: Query<(Entity, &mut LaserBullet)>,
-    time: Res<Time>
+    time: Res<FixedTime>
 ) {
 
     query.for_each_mut(|(entity, mut laser_bullet)| {
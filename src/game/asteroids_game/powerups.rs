@@ -0,0 +1,249 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+use std::time::Duration;
+
+use super::arena::{ArenaMode, Wrapping};
+use super::collision::Collider;
+use super::player::{ActiveShip, FirePattern, LaserShooter, Player, PlayerLives};
+use super::rng::GameRng;
+use super::simulation::{FixedTime, Interpolated};
+use super::spatial_hash::SpatialHash;
+
+const DROP_CHANCE: f64 = 0.12;
+const PICKUP_SIZE: f32 = 12.0;
+const PICKUP_LIFETIME: Duration = Duration::from_secs(10);
+const PICKUP_BLINK_TIME: Duration = Duration::from_secs(3);
+const PICKUP_ROTATION: f32 = 2.0;
+const RAPID_FIRE_COOLDOWN: Duration = Duration::from_millis(80);
+const SPREAD_SHOT: FirePattern = FirePattern::Spread { bolts: 3, angle: 0.5 };
+const SHIELD_COLOR: Color = Color::rgb(0.5, 0.9, 1.0);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PowerUpKind {
+    SpreadShot,
+    Shield,
+    RapidFire,
+    ExtraLife,
+}
+
+impl PowerUpKind {
+    const ALL: [PowerUpKind; 4] = [
+        PowerUpKind::SpreadShot,
+        PowerUpKind::Shield,
+        PowerUpKind::RapidFire,
+        PowerUpKind::ExtraLife,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            PowerUpKind::SpreadShot => "Spread",
+            PowerUpKind::Shield => "Shield",
+            PowerUpKind::RapidFire => "Rapid",
+            PowerUpKind::ExtraLife => "Life",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match *self {
+            PowerUpKind::SpreadShot => Color::rgb(1.0, 0.9, 0.2),
+            PowerUpKind::Shield => SHIELD_COLOR,
+            PowerUpKind::RapidFire => Color::rgb(1.0, 0.4, 0.9),
+            PowerUpKind::ExtraLife => Color::rgb(0.3, 1.0, 0.3),
+        }
+    }
+
+    /// How long the effect lasts, `None` for effects that apply once.
+    fn duration(&self) -> Option<Duration> {
+        match *self {
+            PowerUpKind::SpreadShot => Some(Duration::from_secs(10)),
+            PowerUpKind::Shield => Some(Duration::from_secs(15)),
+            PowerUpKind::RapidFire => Some(Duration::from_secs(8)),
+            PowerUpKind::ExtraLife => None,
+        }
+    }
+}
+
+/// A pickup drifting through the field.
+#[derive(Component)]
+pub struct PowerUp {
+    kind: PowerUpKind,
+    velocity: Vec2,
+    life_time: Timer,
+}
+
+/// Timed effects currently applied to the ship.
+#[derive(Component, Default)]
+pub struct ActivePowerUps {
+    spread_shot: Option<Timer>,
+    shield: Option<Timer>,
+    rapid_fire: Option<Timer>,
+}
+
+impl ActivePowerUps {
+    fn timer_mut(&mut self, kind: PowerUpKind) -> Option<&mut Option<Timer>> {
+        match kind {
+            PowerUpKind::SpreadShot => Some(&mut self.spread_shot),
+            PowerUpKind::Shield => Some(&mut self.shield),
+            PowerUpKind::RapidFire => Some(&mut self.rapid_fire),
+            PowerUpKind::ExtraLife => None,
+        }
+    }
+
+    fn activate(&mut self, kind: PowerUpKind) {
+        if let (Some(timer), Some(duration)) = (self.timer_mut(kind), kind.duration()) {
+            *timer = Some(Timer::new(duration, false));
+        }
+    }
+
+    fn tick(&mut self, delta: Duration) {
+        for timer in [&mut self.spread_shot, &mut self.shield, &mut self.rapid_fire] {
+            if let Some(active) = timer {
+                active.tick(delta);
+
+                if active.finished() {
+                    *timer = None;
+                }
+            }
+        }
+    }
+
+    /// Uses up the shield if there is one, returns whether the hit was absorbed.
+    pub fn consume_shield(&mut self) -> bool {
+        self.shield.take().is_some()
+    }
+
+    pub fn clear(&mut self) {
+        *self = ActivePowerUps::default();
+    }
+
+    /// Active effects with their remaining time, in a stable order for the HUD.
+    pub fn remaining(&self) -> Vec<(PowerUpKind, Duration)> {
+        [
+            (PowerUpKind::SpreadShot, &self.spread_shot),
+            (PowerUpKind::Shield, &self.shield),
+            (PowerUpKind::RapidFire, &self.rapid_fire),
+        ]
+        .into_iter()
+        .filter_map(|(kind, timer)| timer.as_ref().map(|timer| (kind, timer.duration() - timer.elapsed())))
+        .collect()
+    }
+}
+
+/// Gives a destroyed asteroid a chance to leave a random pickup behind.
+pub fn maybe_drop(commands: &mut Commands, rng: &mut GameRng, translation: Vec3) {
+
+    if !rng.gen_bool(DROP_CHANCE) {
+        return;
+    }
+
+    let kind = PowerUpKind::ALL[rng.gen_range(0..PowerUpKind::ALL.len())];
+    let velocity = Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)).normalize_or_zero() * rng.gen_range(10.0..25.0);
+
+    let transform = Transform::from_xyz(translation.x, translation.y, 0.7);
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: kind.color(),
+                custom_size: Some(Vec2::new(PICKUP_SIZE, PICKUP_SIZE)),
+                ..Default::default()
+            },
+            transform,
+            ..Default::default()
+        })
+        .insert(PowerUp {
+            kind,
+            velocity,
+            life_time: Timer::new(PICKUP_LIFETIME, false),
+        })
+        .insert(Collider::polygon(vec![
+            Vec2::new(-PICKUP_SIZE / 2.0, -PICKUP_SIZE / 2.0),
+            Vec2::new(PICKUP_SIZE / 2.0, -PICKUP_SIZE / 2.0),
+            Vec2::new(PICKUP_SIZE / 2.0, PICKUP_SIZE / 2.0),
+            Vec2::new(-PICKUP_SIZE / 2.0, PICKUP_SIZE / 2.0),
+        ]))
+        .insert(Interpolated::new(transform))
+        .insert(Wrapping);
+}
+
+pub fn remove_powerups(mut commands: Commands, query: Query<Entity, With<PowerUp>>) {
+    query.for_each(|entity| {
+        commands.entity(entity).despawn();
+    });
+}
+
+pub fn powerup_movement(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Transform, &mut PowerUp, &mut Visibility)>,
+    time: Res<FixedTime>
+) {
+
+    query.for_each_mut(|(entity, mut transform, mut power_up, mut visibility)| {
+        power_up.life_time.tick(time.delta());
+
+        if power_up.life_time.finished() {
+            commands.entity(entity).despawn();
+            return;
+        }
+
+        transform.translation.x += power_up.velocity.x * time.delta_seconds();
+        transform.translation.y += power_up.velocity.y * time.delta_seconds();
+        transform.rotate(Quat::from_rotation_z(PICKUP_ROTATION * time.delta_seconds()));
+
+        // Blink shortly before the pickup disappears.
+        let remaining = power_up.life_time.duration() - power_up.life_time.elapsed();
+        if remaining < PICKUP_BLINK_TIME {
+            visibility.is_visible = (remaining.as_millis() / 150) % 2 == 0;
+        }
+    });
+}
+
+pub fn powerup_pickup(
+    mut commands: Commands,
+    mut player_query: Query<(&Player, &Transform, &Collider, &mut ActivePowerUps, &mut PlayerLives), ActiveShip>,
+    pickup_query: Query<(Entity, &Transform, &PowerUp, &Collider)>,
+    spatial_hash: Res<SpatialHash>,
+    arena_mode: Res<ArenaMode>
+) {
+
+    let mut ships: Vec<_> = player_query.iter_mut().collect();
+    // When both ships touch a pickup, the first player gets it every time.
+    ships.sort_unstable_by_key(|(player, _, _, _, _)| player.index());
+
+    let mut picked_up = Vec::new();
+
+    for (_, player_transform, player_collider, mut power_ups, mut player_lives) in ships {
+        let nearby = spatial_hash.nearby(player_transform.translation.truncate(), player_collider.bounding_radius());
+
+        nearby.iter().filter_map(|entity| pickup_query.get(*entity).ok()).for_each(|(entity, transform, power_up, collider)| {
+            if !picked_up.contains(&entity) && player_collider.overlaps(player_transform, collider, transform, &arena_mode) {
+                commands.entity(entity).despawn();
+                picked_up.push(entity);
+
+                match power_up.kind {
+                    PowerUpKind::ExtraLife => player_lives.gain_life(),
+                    kind => power_ups.activate(kind),
+                }
+            }
+        });
+    }
+}
+
+/// Ticks the active effects and applies them to the ship's laser and tint.
+pub fn powerup_effects(
+    mut query: Query<(&Player, &mut ActivePowerUps, &mut LaserShooter, &mut Sprite)>,
+    time: Res<FixedTime>
+) {
+
+    query.for_each_mut(|(player, mut power_ups, mut laser_shooter, mut sprite)| {
+        power_ups.tick(time.delta());
+
+        laser_shooter.set_pattern(if power_ups.spread_shot.is_some() { SPREAD_SHOT } else { FirePattern::Single });
+        laser_shooter.set_cooldown(if power_ups.rapid_fire.is_some() { RAPID_FIRE_COOLDOWN } else { LaserShooter::MAX_COOLDOWN });
+
+        let color = if power_ups.shield.is_some() { SHIELD_COLOR } else { player.color() };
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    });
+}
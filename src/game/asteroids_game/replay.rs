@@ -0,0 +1,586 @@
+use bevy::prelude::*;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::f32::consts::TAU;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::game::GameState;
+use crate::game::actions::{Action, Actions, ControlScheme};
+use super::arena::ArenaMode;
+use super::player::{MatchMode, MouseAim, MAX_PLAYERS};
+use super::rng::GameRng;
+use super::simulation::{LatchedActions, TIMESTEP};
+
+const REPLAY_DIR: &str = "replays";
+const REPLAY_EXTENSION: &str = "replay";
+const MAGIC: &[u8; 4] = b"ARPL";
+const VERSION: u8 = 1;
+const HEADER_SIZE: usize = 4 + 1 + 1 + 8 + 4 + 1;
+/// The smallest run, the input of a step without mouse aim followed by its length.
+const MIN_RUN_SIZE: usize = 4 + 1;
+
+/// The actions held during one simulation step, the gameplay systems read these instead of
+/// the controls so a replay can feed them back in.
+///
+/// Analog values are stored at the precision they are saved with, so a recorded run and its
+/// replay see exactly the same numbers.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct TickInput {
+    thrust: u8,
+    reverse: u8,
+    /// Positive turns left, counterclockwise.
+    rotation: i8,
+    fire: bool,
+    hyperspace: bool,
+    /// The simulation does not advance while paused, watching the replay pauses in the same spot.
+    pause: bool,
+    /// Heading the ship turns to with mouse aim, in steps of a full turn divided by 65536.
+    aim: Option<u16>,
+    /// Thrust relative to the screen with mouse aim, right and up.
+    strafe: [i8; 2],
+}
+
+impl TickInput {
+    /// `thrust` and `reverse` go from 0.0 to 1.0, `rotation` from -1.0 to 1.0.
+    pub fn new(thrust: f32, reverse: f32, rotation: f32, fire: bool, hyperspace: bool, pause: bool) -> Self {
+        TickInput {
+            thrust: (thrust.clamp(0.0, 1.0) * u8::MAX as f32).round() as u8,
+            reverse: (reverse.clamp(0.0, 1.0) * u8::MAX as f32).round() as u8,
+            rotation: (rotation.clamp(-1.0, 1.0) * i8::MAX as f32).round() as i8,
+            fire,
+            hyperspace,
+            pause,
+            aim: None,
+            strafe: [0, 0],
+        }
+    }
+
+    /// Steers with mouse aim instead, the ship turns to `heading` and `strafe` pushes it across
+    /// the screen no matter where it faces.
+    pub fn with_aim(self, heading: f32, strafe: Vec2) -> Self {
+        let strafe = strafe.clamp_length_max(1.0) * i8::MAX as f32;
+
+        TickInput {
+            thrust: 0,
+            reverse: 0,
+            rotation: 0,
+            aim: Some((heading.rem_euclid(TAU) / TAU * 65536.0).round() as u32 as u16),
+            strafe: [strafe.x.round() as i8, strafe.y.round() as i8],
+            ..self
+        }
+    }
+
+    pub fn thrust(&self) -> f32 {
+        self.thrust as f32 / u8::MAX as f32
+    }
+
+    pub fn reverse(&self) -> f32 {
+        self.reverse as f32 / u8::MAX as f32
+    }
+
+    pub fn rotation(&self) -> f32 {
+        self.rotation as f32 / i8::MAX as f32
+    }
+
+    pub fn fire(&self) -> bool {
+        self.fire
+    }
+
+    pub fn hyperspace(&self) -> bool {
+        self.hyperspace
+    }
+
+    /// The heading in radians, only set when the ship is steered with mouse aim.
+    pub fn aim(&self) -> Option<f32> {
+        self.aim.map(|aim| aim as f32 / 65536.0 * TAU)
+    }
+
+    pub fn strafe(&self) -> Vec2 {
+        Vec2::new(self.strafe[0] as f32, self.strafe[1] as f32) / i8::MAX as f32
+    }
+
+    /// Four bytes, followed by four more for the heading and the strafe with mouse aim.
+    fn write_bytes(self, bytes: &mut Vec<u8>) {
+        let flags = [self.fire, self.hyperspace, self.pause, self.aim.is_some()]
+            .iter()
+            .enumerate()
+            .fold(0, |bits, (index, pressed)| bits | (*pressed as u8) << index);
+
+        bytes.extend_from_slice(&[flags, self.thrust, self.reverse, self.rotation as u8]);
+
+        if let Some(aim) = self.aim {
+            bytes.extend_from_slice(&aim.to_le_bytes());
+            bytes.extend_from_slice(&[self.strafe[0] as u8, self.strafe[1] as u8]);
+        }
+    }
+
+    /// The input and how many bytes it took, `None` when the bytes end too early.
+    fn read_bytes(bytes: &[u8]) -> Option<(Self, usize)> {
+        let flags = *bytes.first()?;
+        let pressed = |index: u8| flags & (1 << index) != 0;
+        let size = if pressed(3) { 8 } else { 4 };
+        let bytes = bytes.get(..size)?;
+
+        let tick = TickInput {
+            thrust: bytes[1],
+            reverse: bytes[2],
+            rotation: bytes[3] as i8,
+            fire: pressed(0),
+            hyperspace: pressed(1),
+            pause: pressed(2),
+            aim: pressed(3).then(|| u16::from_le_bytes([bytes[4], bytes[5]])),
+            strafe: if pressed(3) { [bytes[6] as i8, bytes[7] as i8] } else { [0, 0] },
+        };
+
+        Some((tick, size))
+    }
+}
+
+/// The input of every ship for the coming step, by player.
+#[derive(Default)]
+pub struct TickInputs([TickInput; MAX_PLAYERS]);
+
+impl TickInputs {
+    pub fn get(&self, player: usize) -> TickInput {
+        self.0[player]
+    }
+}
+
+/// Everything needed to play a run again: the seed, the arena, the players and the input of
+/// every step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    seed: u64,
+    arena_mode: ArenaMode,
+    match_mode: MatchMode,
+    /// The inputs of every player, each as long as the run.
+    ticks: Vec<Vec<TickInput>>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, arena_mode: ArenaMode, match_mode: MatchMode, ticks: Vec<Vec<TickInput>>) -> Self {
+        Replay {
+            seed,
+            arena_mode,
+            match_mode,
+            ticks,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn arena_mode(&self) -> ArenaMode {
+        self.arena_mode
+    }
+
+    pub fn match_mode(&self) -> MatchMode {
+        self.match_mode
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.step_count() as f64 * TIMESTEP)
+    }
+
+    fn step_count(&self) -> usize {
+        self.ticks.first().map_or(0, Vec::len)
+    }
+
+    /// A small header followed by the inputs of every player run length encoded, held buttons
+    /// rarely change from one step to the next.
+    pub fn to_bytes(&self) -> Vec<u8> {
+
+        let mut bytes = Vec::with_capacity(HEADER_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(match self.arena_mode {
+            ArenaMode::Open => 0,
+            ArenaMode::Wrap => 1,
+        });
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&(self.step_count() as u32).to_le_bytes());
+        bytes.push(match self.match_mode {
+            MatchMode::Solo => 0,
+            MatchMode::Coop => 1,
+            MatchMode::Versus => 2,
+        });
+
+        for player_ticks in &self.ticks {
+            let mut ticks = player_ticks.iter().peekable();
+            while let Some(tick) = ticks.next() {
+                let mut run: u8 = 1;
+                while run < u8::MAX && ticks.next_if_eq(&tick).is_some() {
+                    run += 1;
+                }
+
+                tick.write_bytes(&mut bytes);
+                bytes.push(run);
+            }
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+
+        if bytes.len() < HEADER_SIZE || &bytes[0..4] != MAGIC {
+            return Err(invalid_data("not a replay file"));
+        }
+        if bytes[4] != VERSION {
+            return Err(invalid_data("unsupported replay version"));
+        }
+
+        let arena_mode = match bytes[5] {
+            0 => ArenaMode::Open,
+            1 => ArenaMode::Wrap,
+            _ => return Err(invalid_data("unknown arena mode")),
+        };
+        let seed = u64::from_le_bytes(bytes[6..14].try_into().unwrap());
+        let tick_count = u32::from_le_bytes(bytes[14..18].try_into().unwrap()) as usize;
+
+        let match_mode = match bytes[18] {
+            0 => MatchMode::Solo,
+            1 => MatchMode::Coop,
+            2 => MatchMode::Versus,
+            _ => return Err(invalid_data("unknown match mode")),
+        };
+        let mut runs = &bytes[HEADER_SIZE..];
+
+        // Every run is the input of a step followed by how many steps it was held, the runs of
+        // one player follow those of the player before.
+        let mut ticks = Vec::with_capacity(match_mode.player_count());
+
+        for _ in 0..match_mode.player_count() {
+            // The count comes from the file, a broken one must not reserve more than the runs can hold.
+            let mut player_ticks = Vec::with_capacity(tick_count.min(runs.len() / MIN_RUN_SIZE * u8::MAX as usize));
+
+            while player_ticks.len() < tick_count {
+                let (tick, size) = TickInput::read_bytes(runs).ok_or_else(|| invalid_data("truncated replay"))?;
+                let run = *runs.get(size).ok_or_else(|| invalid_data("truncated replay"))?;
+
+                player_ticks.extend(std::iter::repeat_n(tick, run as usize));
+                runs = &runs[size + 1..];
+            }
+
+            if player_ticks.len() != tick_count {
+                return Err(invalid_data("runs do not match the length of the replay"));
+            }
+
+            ticks.push(player_ticks);
+        }
+
+        if !runs.is_empty() {
+            return Err(invalid_data("runs do not match the length of the replay"));
+        }
+
+        Ok(Replay::new(seed, arena_mode, match_mode, ticks))
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Replay::from_bytes(&fs::read(path)?)
+    }
+
+    /// Writes the replay into the replay directory, named after the millisecond it was saved.
+    /// Never overwrites another replay, the name moves on to the next free millisecond.
+    pub fn save(&self) -> io::Result<PathBuf> {
+
+        fs::create_dir_all(REPLAY_DIR)?;
+
+        let mut timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+
+        loop {
+            let path = Path::new(REPLAY_DIR).join(format!("{}.{}", timestamp, REPLAY_EXTENSION));
+
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    file.write_all(&self.to_bytes())?;
+                    return Ok(path);
+                },
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => timestamp += 1,
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+
+/// Saved replays, the newest first.
+pub fn list_replays() -> Vec<PathBuf> {
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(REPLAY_DIR)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|extension| extension == REPLAY_EXTENSION))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    paths.sort_unstable_by(|a, b| b.cmp(a));
+
+    paths
+}
+
+/// Replay picked in the menu, the next run plays it back instead of reading the controls.
+#[derive(Default)]
+pub struct ReplaySelection {
+    replay: Option<Replay>,
+    /// The arena and match mode picked in the menu, put back once the replay has been watched.
+    menu_modes: Option<(ArenaMode, MatchMode)>,
+}
+
+impl ReplaySelection {
+    /// Plays `replay` in the next run, in the arena and with the players it was recorded with.
+    pub fn watch(&mut self, replay: Replay, arena_mode: &mut ArenaMode, match_mode: &mut MatchMode) {
+        self.menu_modes = Some((*arena_mode, *match_mode));
+        *arena_mode = replay.arena_mode();
+        *match_mode = replay.match_mode();
+        self.replay = Some(replay);
+    }
+
+    pub fn replay(&self) -> Option<&Replay> {
+        self.replay.as_ref()
+    }
+}
+
+/// Inputs of the run being played, saved when it ends.
+pub struct ReplayRecorder {
+    ticks: Vec<Vec<TickInput>>,
+}
+
+pub struct ReplayPlayback {
+    replay: Replay,
+    tick: usize,
+    menu_modes: Option<(ArenaMode, MatchMode)>,
+}
+
+pub fn replay_setup(mut commands: Commands, mut selection: ResMut<ReplaySelection>, match_mode: Res<MatchMode>) {
+    match selection.replay.take() {
+        Some(replay) => commands.insert_resource(ReplayPlayback {
+            replay,
+            tick: 0,
+            menu_modes: selection.menu_modes.take(),
+        }),
+        None => commands.insert_resource(ReplayRecorder { ticks: vec![Vec::new(); match_mode.player_count()] }),
+    }
+}
+
+/// Saves the run that just ended, or puts the menu selection back after watching a replay.
+pub fn save_replay(
+    mut commands: Commands,
+    recorder: Option<Res<ReplayRecorder>>,
+    playback: Option<Res<ReplayPlayback>>,
+    rng: Res<GameRng>,
+    mut arena_mode: ResMut<ArenaMode>,
+    mut match_mode: ResMut<MatchMode>
+) {
+
+    if let Some(recorder) = recorder {
+        if recorder.ticks.iter().any(|ticks| !ticks.is_empty()) {
+            let replay = Replay::new(rng.seed(), *arena_mode, *match_mode, recorder.ticks.clone());
+
+            match replay.save() {
+                Ok(path) => info!("Saved replay to {}", path.display()),
+                Err(error) => warn!("Could not save replay: {}", error),
+            }
+        }
+    }
+
+    if let Some((menu_arena_mode, menu_match_mode)) = playback.and_then(|playback| playback.menu_modes) {
+        *arena_mode = menu_arena_mode;
+        *match_mode = menu_match_mode;
+    }
+
+    commands.remove_resource::<ReplayRecorder>();
+    commands.remove_resource::<ReplayPlayback>();
+}
+
+/// Fills in the input of every ship for the coming step, from the players' controls or from the
+/// replay being watched.
+#[allow(clippy::too_many_arguments)]
+pub fn read_input(
+    mut inputs: ResMut<TickInputs>,
+    actions: Actions,
+    latched_actions: Res<LatchedActions>,
+    match_mode: Res<MatchMode>,
+    control_scheme: Res<ControlScheme>,
+    mouse_aim: MouseAim,
+    mut recorder: Option<ResMut<ReplayRecorder>>,
+    playback: Option<ResMut<ReplayPlayback>>,
+    mut game_state: ResMut<State<GameState>>
+) {
+
+    if let Some(mut playback) = playback {
+        let tick = playback.tick;
+
+        if tick < playback.replay.step_count() {
+            for (input, ticks) in inputs.0.iter_mut().zip(&playback.replay.ticks) {
+                *input = ticks[tick];
+            }
+            playback.tick += 1;
+
+            if inputs.0.iter().any(|input| input.pause) {
+                let _ = game_state.push(GameState::Pause);
+            }
+        } else {
+            // Runs that were quit before the game was over end here.
+            *inputs = TickInputs::default();
+            let _ = game_state.set(GameState::Menu);
+        }
+        return;
+    }
+
+    let player_count = match_mode.player_count();
+
+    for index in 0..player_count {
+        let player_actions = actions.player(index, player_count);
+        let just_pressed = |action| latched_actions.just_pressed(index, action);
+
+        // There is only one mouse, it steers the first ship. With the cursor outside the window
+        // the ship keeps its heading rather than falling back to the rotate controls.
+        let heading = match *control_scheme {
+            ControlScheme::MouseAim if index == 0 => mouse_aim.heading().or_else(|| mouse_aim.facing()),
+            _ => None,
+        };
+
+        let input = match heading {
+            Some(heading) => {
+                // Thrust and rotate move the ship up, down, left and right on the screen.
+                let strafe = Vec2::new(
+                    player_actions.value(Action::RotateRight) - player_actions.value(Action::RotateLeft),
+                    player_actions.value(Action::Thrust) - player_actions.value(Action::Reverse)
+                );
+
+                TickInput::new(
+                    0.0,
+                    0.0,
+                    0.0,
+                    player_actions.pressed(Action::Fire) || mouse_aim.fire(),
+                    just_pressed(Action::Hyperspace),
+                    just_pressed(Action::Pause)
+                )
+                .with_aim(heading, strafe)
+            },
+            None => TickInput::new(
+                player_actions.value(Action::Thrust),
+                player_actions.value(Action::Reverse),
+                player_actions.value(Action::RotateLeft) - player_actions.value(Action::RotateRight),
+                player_actions.pressed(Action::Fire),
+                just_pressed(Action::Hyperspace),
+                just_pressed(Action::Pause)
+            ),
+        };
+
+        inputs.0[index] = input;
+
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.ticks[index].push(input);
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_ticks() -> Vec<TickInput> {
+        let thrust = TickInput::new(1.0, 0.0, 0.0, false, false, false);
+        let firing = TickInput::new(0.4, 0.0, 0.75, true, false, false);
+        let jump = TickInput::new(0.0, 0.0, 0.0, false, true, true);
+
+        std::iter::repeat_n(thrust, 300)
+            .chain(std::iter::repeat_n(firing, 20))
+            .chain(std::iter::once(jump))
+            .chain(std::iter::repeat_n(TickInput::default(), 40))
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let replay = Replay::new(0xDEAD_BEEF_1234, ArenaMode::Wrap, MatchMode::Solo, vec![sample_ticks()]);
+
+        assert_eq!(Replay::from_bytes(&replay.to_bytes()).unwrap(), replay);
+    }
+
+    #[test]
+    fn keeps_the_inputs_of_every_player() {
+        let mut second = sample_ticks();
+        second.reverse();
+        let replay = Replay::new(5, ArenaMode::Open, MatchMode::Coop, vec![sample_ticks(), second.clone()]);
+        assert_eq!(Replay::from_bytes(&replay.to_bytes()).unwrap(), replay);
+
+        let replay = Replay::new(5, ArenaMode::Wrap, MatchMode::Versus, vec![sample_ticks(), second]);
+        assert_eq!(Replay::from_bytes(&replay.to_bytes()).unwrap(), replay);
+    }
+
+    #[test]
+    fn held_inputs_are_stored_compactly() {
+        let replay = Replay::new(1, ArenaMode::Open, MatchMode::Solo, vec![sample_ticks()]);
+
+        // 300 steps of thrust need two runs, then one run each for the rest.
+        assert_eq!(replay.to_bytes().len(), HEADER_SIZE + 5 * 5);
+    }
+
+    #[test]
+    fn keys_push_all_the_way_and_sticks_part_way() {
+        let keys = TickInput::new(1.0, 0.0, -1.0, false, false, false);
+        assert_eq!(keys.thrust(), 1.0);
+        assert_eq!(keys.rotation(), -1.0);
+
+        let stick = TickInput::new(0.3, 0.0, 0.5, false, false, false);
+        assert!((stick.thrust() - 0.3).abs() < 0.01);
+        assert!((stick.rotation() - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn mouse_aim_round_trips_through_bytes() {
+        let aimed = TickInput::new(0.0, 0.0, 0.0, true, false, false).with_aim(-1.0, Vec2::new(1.0, 1.0));
+        let replay = Replay::new(3, ArenaMode::Open, MatchMode::Solo, vec![vec![aimed, aimed, TickInput::default()]]);
+        let bytes = replay.to_bytes();
+
+        // Only the aimed run carries the heading and the strafe.
+        assert_eq!(bytes.len(), HEADER_SIZE + 9 + 5);
+        assert_eq!(Replay::from_bytes(&bytes).unwrap(), replay);
+
+        assert!((aimed.aim().unwrap() - (TAU - 1.0)).abs() < 0.001);
+        assert!((aimed.strafe().length() - 1.0).abs() < 0.01);
+        assert_eq!(TickInput::default().aim(), None);
+    }
+
+    #[test]
+    fn watching_a_replay_remembers_the_menu_selection() {
+        let replay = Replay::new(4, ArenaMode::Wrap, MatchMode::Versus, vec![sample_ticks(), sample_ticks()]);
+        let (mut arena_mode, mut match_mode) = (ArenaMode::Open, MatchMode::Coop);
+        let mut selection = ReplaySelection::default();
+
+        selection.watch(replay.clone(), &mut arena_mode, &mut match_mode);
+
+        assert_eq!((arena_mode, match_mode), (ArenaMode::Wrap, MatchMode::Versus));
+        assert_eq!(selection.replay(), Some(&replay));
+        assert_eq!(selection.menu_modes, Some((ArenaMode::Open, MatchMode::Coop)));
+    }
+
+    #[test]
+    fn rejects_broken_files() {
+        let bytes = Replay::new(1, ArenaMode::Open, MatchMode::Solo, vec![sample_ticks()]).to_bytes();
+
+        assert!(Replay::from_bytes(b"not a replay at all").is_err());
+        assert!(Replay::from_bytes(&bytes[..bytes.len() - 5]).is_err());
+        assert!(Replay::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut other_version = bytes.clone();
+        other_version[4] = VERSION + 1;
+        assert!(Replay::from_bytes(&other_version).is_err());
+
+        // Far more steps than the runs hold.
+        let mut endless = bytes;
+        endless[14..18].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Replay::from_bytes(&endless).is_err());
+    }
+}
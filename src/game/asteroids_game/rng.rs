@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+use super::replay::ReplaySelection;
+
+/// Source of every random gameplay decision, the same seed with the same inputs plays the same run.
+pub struct GameRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+/// Seed passed on the command line with `--seed <number>`, every run starts from it when set.
+#[derive(Clone, Copy, Default)]
+pub struct SeedOverride(Option<u64>);
+
+impl SeedOverride {
+    pub fn from_args() -> Self {
+        SeedOverride(parse_seed(std::env::args().skip(1)))
+    }
+}
+
+/// A replay brings its own seed, otherwise the one from the command line or a random one is used.
+pub fn rng_setup(mut commands: Commands, seed_override: Res<SeedOverride>, selection: Res<ReplaySelection>) {
+    let seed = match selection.replay() {
+        Some(replay) => replay.seed(),
+        None => seed_override.0.unwrap_or_else(rand::random),
+    };
+
+    commands.insert_resource(GameRng::new(seed));
+}
+
+pub fn remove_rng(mut commands: Commands) {
+    commands.remove_resource::<GameRng>();
+}
+
+fn parse_seed(mut args: impl Iterator<Item = String>) -> Option<u64> {
+
+    while let Some(arg) = args.next() {
+        let value = if arg == "--seed" {
+            args.next()
+        } else {
+            arg.strip_prefix("--seed=").map(str::to_string)
+        };
+
+        if let Some(value) = value {
+            match value.parse() {
+                Ok(seed) => return Some(seed),
+                Err(_) => warn!("Ignoring invalid seed {:?}, expected a number", value),
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn same_seed_gives_same_sequence() {
+        let mut a = GameRng::new(42);
+        let mut b = GameRng::new(42);
+        let mut c = GameRng::new(43);
+
+        let sequence_a: Vec<f32> = (0..16).map(|_| a.gen_range(0.0..1.0)).collect();
+        let sequence_b: Vec<f32> = (0..16).map(|_| b.gen_range(0.0..1.0)).collect();
+        let sequence_c: Vec<f32> = (0..16).map(|_| c.gen_range(0.0..1.0)).collect();
+
+        assert_eq!(sequence_a, sequence_b);
+        assert_ne!(sequence_a, sequence_c);
+        assert_eq!(a.seed(), 42);
+    }
+
+    #[test]
+    fn parses_seed_from_command_line() {
+        assert_eq!(parse_seed(args(&["--seed", "1234"])), Some(1234));
+        assert_eq!(parse_seed(args(&["--fullscreen", "--seed=99"])), Some(99));
+        assert_eq!(parse_seed(args(&["--seed", "abc"])), None);
+        assert_eq!(parse_seed(args(&[])), None);
+    }
+}
@@ -0,0 +1,404 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use super::arena::{ArenaMode, Wrapping, ARENA_SIZE};
+use super::asteroids::{self, Asteroid, AsteroidsAtlas, AsteroidsStats};
+use super::collision::Collider;
+use super::hyperspace::InHyperspace;
+use super::particles::{self, ASTEROID_DEBRIS, LASER_SPARKS, SHIP_EXPLOSION};
+use super::player::{self, Hostile, LaserBullet, LaserShooter, MatchMode, Player, PlayerLives, PlayerRespawn, VulnerableShip};
+use super::powerups::ActivePowerUps;
+use super::rng::GameRng;
+use super::simulation::{FixedTime, Interpolated};
+use super::spatial_hash::SpatialHash;
+use super::waves::Waves;
+
+const SAUCER_SPAWN_INTERVAL: Duration = Duration::from_secs(25);
+const SAUCER_LIFETIME: Duration = Duration::from_secs(12);
+const SAUCER_COURSE_CHANGE: Duration = Duration::from_secs(2);
+const SAUCER_SPAWN_DISTANCE: f32 = 350.0;
+const SMALL_SAUCER_CHANCE_PER_WAVE: f64 = 0.15;
+const SMALL_SAUCER_MAX_CHANCE: f64 = 0.8;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SaucerSize {
+    Large,
+    Small,
+}
+
+impl SaucerSize {
+    pub fn radius(&self) -> f32 {
+        match *self {
+            SaucerSize::Large => 20.0,
+            SaucerSize::Small => 12.0,
+        }
+    }
+
+    pub fn sprite_size(&self) -> f32 {
+        match *self {
+            SaucerSize::Large => 40.0,
+            SaucerSize::Small => 24.0,
+        }
+    }
+
+    pub fn score(&self) -> u32 {
+        match *self {
+            SaucerSize::Large => 200,
+            SaucerSize::Small => 1000,
+        }
+    }
+
+    fn speed(&self) -> f32 {
+        match *self {
+            SaucerSize::Large => 60.0,
+            SaucerSize::Small => 90.0,
+        }
+    }
+
+    fn fire_interval(&self) -> Duration {
+        match *self {
+            SaucerSize::Large => Duration::from_millis(1500),
+            SaucerSize::Small => Duration::from_millis(1000),
+        }
+    }
+
+    fn color(&self) -> Color {
+        match *self {
+            SaucerSize::Large => Color::rgb(1.0, 0.6, 0.2),
+            SaucerSize::Small => Color::rgb(1.0, 0.25, 0.25),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Saucer {
+    size: SaucerSize,
+    velocity: Vec2,
+    fire_timer: Timer,
+    course_timer: Timer,
+    life_time: Timer,
+}
+
+pub struct SaucerSpawner {
+    spawn_timer: Timer,
+}
+
+pub fn saucers_setup(mut commands: Commands) {
+    commands.insert_resource(SaucerSpawner {
+        spawn_timer: Timer::new(SAUCER_SPAWN_INTERVAL, true),
+    });
+}
+
+pub fn remove_saucers(
+    mut commands: Commands,
+    saucer_query: Query<Entity, With<Saucer>>,
+    hostile_query: Query<Entity, With<Hostile>>
+) {
+    saucer_query.for_each(|entity| {
+        commands.entity(entity).despawn();
+    });
+    hostile_query.for_each(|entity| {
+        commands.entity(entity).despawn();
+    });
+
+    commands.remove_resource::<SaucerSpawner>();
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_saucer(
+    mut commands: Commands,
+    mut spawner: ResMut<SaucerSpawner>,
+    asset_server: Res<AssetServer>,
+    waves: Res<Waves>,
+    arena_mode: Res<ArenaMode>,
+    match_mode: Res<MatchMode>,
+    player_query: Query<(&Transform, &Player, &PlayerLives)>,
+    mut rng: ResMut<GameRng>,
+    time: Res<FixedTime>
+) {
+
+    // The ships only have each other to worry about in versus.
+    if *match_mode == MatchMode::Versus {
+        return;
+    }
+
+    spawner.spawn_timer.tick(time.delta());
+
+    if !spawner.spawn_timer.just_finished() || waves.in_breather() {
+        return;
+    }
+
+    let small_chance = (SMALL_SAUCER_CHANCE_PER_WAVE * (waves.number() - 1) as f64).min(SMALL_SAUCER_MAX_CHANCE);
+    let size = if rng.gen_bool(small_chance) { SaucerSize::Small } else { SaucerSize::Large };
+
+    // Saucers enter from the left or right and fly across the field.
+    let direction = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+
+    let translation = match *arena_mode {
+        ArenaMode::Open => {
+            let player_translation = player::random_player_translation(&player_query, &mut rng);
+            Vec3::new(
+                player_translation.x - direction * SAUCER_SPAWN_DISTANCE,
+                player_translation.y + rng.gen_range(-150.0..150.0),
+                0.6
+            )
+        },
+        ArenaMode::Wrap => Vec3::new(
+            -direction * ARENA_SIZE.x / 2.0,
+            rng.gen_range(-ARENA_SIZE.y / 2.0..ARENA_SIZE.y / 2.0),
+            0.6
+        ),
+    };
+
+    let sprite_size = size.sprite_size();
+    let transform = Transform {
+        translation,
+        rotation: Quat::from_rotation_z(PI),
+        ..Default::default()
+    };
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: size.color(),
+                custom_size: Some(Vec2::new(sprite_size, sprite_size)),
+                anchor: bevy::sprite::Anchor::Center,
+                ..Default::default()
+            },
+            texture: asset_server.load("images/ship.png"),
+            transform,
+            ..Default::default()
+        })
+        .insert(Saucer {
+            size,
+            velocity: Vec2::new(direction * size.speed(), 0.0),
+            fire_timer: Timer::new(size.fire_interval(), true),
+            course_timer: Timer::new(SAUCER_COURSE_CHANGE, true),
+            life_time: Timer::new(SAUCER_LIFETIME, false),
+        })
+        .insert(player::ship_collider(sprite_size))
+        .insert(Interpolated::new(transform))
+        .insert(Wrapping);
+}
+
+pub fn saucer_movement(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Transform, &mut Saucer)>,
+    mut rng: ResMut<GameRng>,
+    time: Res<FixedTime>
+) {
+
+    query.for_each_mut(|(entity, mut transform, mut saucer)| {
+        saucer.life_time.tick(time.delta());
+
+        if saucer.life_time.finished() {
+            commands.entity(entity).despawn();
+            return;
+        }
+
+        saucer.course_timer.tick(time.delta());
+
+        if saucer.course_timer.just_finished() {
+            let vertical = [-0.5, 0.0, 0.5][rng.gen_range(0..3)];
+            saucer.velocity.y = vertical * saucer.size.speed();
+        }
+
+        transform.translation.x += saucer.velocity.x * time.delta_seconds();
+        transform.translation.y += saucer.velocity.y * time.delta_seconds();
+    });
+}
+
+#[allow(clippy::type_complexity)]
+pub fn saucer_shoot(
+    mut commands: Commands,
+    mut saucer_query: Query<(&Transform, &mut Saucer)>,
+    player_query: Query<&Transform, (With<Player>, Without<PlayerRespawn>, Without<InHyperspace>)>,
+    asset_server: Res<AssetServer>,
+    arena_mode: Res<ArenaMode>,
+    mut rng: ResMut<GameRng>,
+    time: Res<FixedTime>
+) {
+
+    let player_translations: Vec<Vec2> = player_query.iter().map(|transform| transform.translation.truncate()).collect();
+
+    saucer_query.for_each_mut(|(saucer_transform, mut saucer)| {
+        saucer.fire_timer.tick(time.delta());
+
+        if !saucer.fire_timer.just_finished() {
+            return;
+        }
+
+        let saucer_translation = saucer_transform.translation.truncate();
+        let player_translation = player_translations.iter().copied().min_by(|a, b| {
+            arena_mode
+                .distance(saucer_translation, *a)
+                .total_cmp(&arena_mode.distance(saucer_translation, *b))
+        });
+
+        // The small saucer aims at the closest ship, slightly off, the large one fires anywhere.
+        let angle = match (saucer.size, player_translation) {
+            (SaucerSize::Small, Some(player_translation)) => {
+                let offset = arena_mode.offset(saucer_translation, player_translation);
+                (-offset.x).atan2(offset.y) + rng.gen_range(-0.25..0.25)
+            },
+            _ => rng.gen_range(0.0..2.0 * PI),
+        };
+
+        let direction = vec2_from_circle(angle, 1.0);
+        let translation = saucer_transform.translation + (direction * saucer.size.radius()).extend(0.0);
+
+        let transform = Transform {
+            translation,
+            rotation: Quat::from_rotation_z(angle),
+            ..Default::default()
+        };
+
+        player::spawn_laser(
+            &mut commands,
+            asset_server.load("images/laser.png"),
+            transform,
+            direction * LaserShooter::SPEED,
+            None
+        )
+        .insert(Hostile);
+    });
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn saucer_collision(
+    mut commands: Commands,
+    saucer_query: Query<(Entity, &Transform, &Saucer, &Collider)>,
+    laser_query: Query<(Entity, &Transform, &LaserBullet, &Collider), Without<Hostile>>,
+    mut asteroid_query: Query<(Entity, &Transform, &mut Asteroid, &Collider)>,
+    mut player_query: Query<(Entity, &Player, &Transform, &Collider, &mut Visibility, &mut ActivePowerUps, &mut PlayerLives), VulnerableShip>,
+    spatial_hash: Res<SpatialHash>,
+    asteroids_atlas: Res<AsteroidsAtlas>,
+    arena_mode: Res<ArenaMode>,
+    mut asteroid_stats: ResMut<AsteroidsStats>,
+    mut rng: ResMut<GameRng>,
+    time: Res<FixedTime>
+) {
+
+    // Every ship can only be destroyed once per step.
+    let mut ships: Vec<_> = player_query.iter_mut().collect();
+    ships.sort_unstable_by_key(|(_, player, _, _, _, _, _)| player.index());
+
+    let mut shot_saucers = Vec::new();
+
+    // Lasers are hashed where they are now, but may have swept over a saucer on their way there.
+    for (laser, laser_transform, laser_bullet, laser_collider) in laser_query.iter() {
+        // Already stopped by an asteroid in this step.
+        if laser_bullet.spent() {
+            continue;
+        }
+
+        let displacement = laser_bullet.velocity() * time.delta_seconds();
+        let nearby = spatial_hash.nearby_swept(laser_transform.translation.truncate(), laser_collider.bounding_radius(), displacement);
+
+        let saucer_hit = nearby.iter().filter_map(|entity| saucer_query.get(*entity).ok()).find(|(saucer_entity, saucer_transform, _, saucer_collider)| {
+            !shot_saucers.contains(saucer_entity)
+                && laser_collider.swept_overlaps(laser_transform, displacement, saucer_collider, saucer_transform, &arena_mode)
+        });
+
+        if let Some((saucer_entity, saucer_transform, saucer, _)) = saucer_hit {
+            commands.entity(laser).despawn();
+            commands.entity(saucer_entity).despawn();
+            shot_saucers.push(saucer_entity);
+            particles::spawn_burst(&mut commands, LASER_SPARKS, 6, laser_transform.translation);
+            particles::spawn_burst(&mut commands, SHIP_EXPLOSION, 20, saucer_transform.translation);
+
+            asteroid_stats.shot_hit();
+            if let Some(shooter) = laser_bullet.shooter() {
+                asteroid_stats.bonus(shooter, saucer.size.score());
+            }
+        }
+    }
+
+    for (saucer_entity, saucer_transform, _, saucer_collider) in saucer_query.iter() {
+        if shot_saucers.contains(&saucer_entity) {
+            continue;
+        }
+
+        let nearby = spatial_hash.nearby(saucer_transform.translation.truncate(), saucer_collider.bounding_radius());
+
+        // Lasers, ships and other saucers may already have broken an asteroid in this step.
+        let asteroid_hit = nearby.iter().copied().find(|entity| {
+            match asteroid_query.get(*entity) {
+                Ok((_, asteroid_transform, asteroid, asteroid_collider)) =>
+                    !asteroid.broken() && saucer_collider.overlaps(saucer_transform, asteroid_collider, asteroid_transform, &arena_mode),
+                Err(_) => false,
+            }
+        });
+
+        if let Some(asteroid_entity) = asteroid_hit {
+            let (_, asteroid_transform, mut asteroid, _) = asteroid_query.get_mut(asteroid_entity).unwrap();
+
+            commands.entity(asteroid_entity).despawn();
+            asteroid.mark_broken();
+            commands.entity(saucer_entity).despawn();
+            particles::spawn_burst(&mut commands, ASTEROID_DEBRIS, 10, asteroid_transform.translation);
+            particles::spawn_burst(&mut commands, SHIP_EXPLOSION, 20, saucer_transform.translation);
+
+            asteroid_stats.removed();
+            asteroids::spawn_fragments(
+                &mut commands,
+                &asteroids_atlas,
+                &mut asteroid_stats,
+                &mut rng,
+                &asteroid,
+                asteroid_transform.translation
+            );
+            continue;
+        }
+
+        let rammed = ships.iter().position(|(_, _, player_transform, player_collider, _, _, _)| {
+            saucer_collider.overlaps(saucer_transform, player_collider, player_transform, &arena_mode)
+        });
+
+        if let Some(index) = rammed {
+            let (player_entity, _, player_transform, _, mut visibility, mut power_ups, mut player_lives) = ships.remove(index);
+
+            commands.entity(saucer_entity).despawn();
+            particles::spawn_burst(&mut commands, SHIP_EXPLOSION, 20, saucer_transform.translation);
+            player::destroy_player(&mut commands, player_entity, player_transform.translation, &mut visibility, &mut power_ups, &mut player_lives);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn hostile_laser_collision(
+    mut commands: Commands,
+    laser_query: Query<(Entity, &Transform, &LaserBullet, &Collider), With<Hostile>>,
+    mut player_query: Query<(Entity, &Player, &Transform, &Collider, &mut Visibility, &mut ActivePowerUps, &mut PlayerLives), VulnerableShip>,
+    spatial_hash: Res<SpatialHash>,
+    arena_mode: Res<ArenaMode>,
+    time: Res<FixedTime>
+) {
+
+    let mut ships: Vec<_> = player_query.iter_mut().collect();
+    ships.sort_unstable_by_key(|(_, player, _, _, _, _, _)| player.index());
+
+    for (laser, laser_transform, laser_bullet, laser_collider) in laser_query.iter() {
+        let displacement = laser_bullet.velocity() * time.delta_seconds();
+        let nearby = spatial_hash.nearby_swept(laser_transform.translation.truncate(), laser_collider.bounding_radius(), displacement);
+
+        // A laser stops at the first ship it hits, and every ship is only hit once per step.
+        let hit = ships.iter().position(|(player, _, player_transform, player_collider, _, _, _)| {
+            nearby.contains(player)
+                && laser_collider.swept_overlaps(laser_transform, displacement, player_collider, player_transform, &arena_mode)
+        });
+
+        if let Some(index) = hit {
+            let (player, _, player_transform, _, mut visibility, mut power_ups, mut player_lives) = ships.remove(index);
+
+            commands.entity(laser).despawn();
+            player::destroy_player(&mut commands, player, player_transform.translation, &mut visibility, &mut power_ups, &mut player_lives);
+        }
+    }
+}
+
+fn vec2_from_circle(angle: f32, radius: f32) -> Vec2 {
+    Vec2::new(-angle.sin() * radius, angle.cos() * radius)
+}
@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+use bevy::core::FixedTimesteps;
+use bevy::ecs::schedule::{ShouldRun, StageLabel};
+use bevy::utils::HashSet;
+use std::time::Duration;
+
+use crate::game::GameState;
+use crate::game::actions::{Action, Actions};
+use super::arena::ArenaMode;
+use super::player::{MatchMode, MAX_PLAYERS};
+
+/// Length of one simulation step in seconds, the gameplay runs at 60 steps per second.
+pub const TIMESTEP: f64 = 1.0 / 60.0;
+pub const TIMESTEP_LABEL: &str = "asteroids_simulation";
+
+/// Anything that moves further than this in a single step jumped there, like a ship coming
+/// out of hyperspace, and is not interpolated.
+const TELEPORT_DISTANCE: f32 = 50.0;
+
+#[derive(StageLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SimulationStage {
+    /// Runs the stages below as often as needed to keep up with the fixed timestep.
+    FixedUpdate,
+    Begin,
+    Update,
+    End,
+}
+
+/// Time that passes in one simulation step, used in place of `Time` by the fixed step systems.
+pub struct FixedTime {
+    delta: Duration,
+}
+
+impl Default for FixedTime {
+    fn default() -> Self {
+        FixedTime {
+            delta: Duration::from_secs_f64(TIMESTEP),
+        }
+    }
+}
+
+impl FixedTime {
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+}
+
+/// Actions every player pressed since the last simulation step, so a press is neither lost when
+/// a frame runs no step nor repeated when it runs several.
+#[derive(Default)]
+pub struct LatchedActions {
+    just_pressed: [HashSet<Action>; MAX_PLAYERS],
+}
+
+impl LatchedActions {
+    pub fn just_pressed(&self, player: usize, action: Action) -> bool {
+        self.just_pressed[player].contains(&action)
+    }
+}
+
+/// The simulated transforms of the last two steps, what is drawn is blended between them.
+#[derive(Component, Clone, Copy)]
+pub struct Interpolated {
+    previous: Transform,
+    current: Transform,
+}
+
+impl Interpolated {
+    pub fn new(transform: Transform) -> Self {
+        Interpolated {
+            previous: transform,
+            current: transform,
+        }
+    }
+}
+
+/// Gameplay only advances while a round is played. `SystemSet::on_update` cannot be used for
+/// this, its run criteria keep looping in any stage other than the one driving the state.
+pub fn run_if_playing(game_state: Res<State<GameState>>) -> ShouldRun {
+    if *game_state.current() == GameState::Asteroids {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+pub fn latch_actions(actions: Actions, match_mode: Res<MatchMode>, mut latched_actions: ResMut<LatchedActions>) {
+    let player_count = match_mode.player_count();
+
+    for (index, just_pressed) in latched_actions.just_pressed.iter_mut().take(player_count).enumerate() {
+        let player_actions = actions.player(index, player_count);
+        just_pressed.extend(Action::ALL.into_iter().filter(|action| player_actions.just_pressed(*action)));
+    }
+}
+
+/// Puts the simulated transforms back in place of the blended ones before a step.
+pub fn begin_step(mut query: Query<(&mut Transform, &mut Interpolated)>) {
+    query.for_each_mut(|(mut transform, mut interpolated)| {
+        *transform = interpolated.current;
+        interpolated.previous = interpolated.current;
+    });
+}
+
+pub fn end_step(mut query: Query<(&Transform, &mut Interpolated)>, mut latched_actions: ResMut<LatchedActions>) {
+
+    query.for_each_mut(|(transform, mut interpolated)| {
+        interpolated.current = *transform;
+    });
+
+    latched_actions.just_pressed.iter_mut().for_each(HashSet::clear);
+}
+
+/// Blends every interpolated transform by how far the clock has advanced into the next step.
+pub fn interpolate_transforms(
+    mut query: Query<(&mut Transform, &Interpolated)>,
+    fixed_timesteps: Res<FixedTimesteps>,
+    arena_mode: Res<ArenaMode>
+) {
+
+    let alpha = fixed_timesteps
+        .get(TIMESTEP_LABEL)
+        .map_or(1.0, |timestep| timestep.overstep_percentage().min(1.0) as f32);
+
+    query.for_each_mut(|(mut transform, interpolated)| {
+        let previous = interpolated.previous;
+        let current = interpolated.current;
+
+        // Entities that wrapped around come in from the edge instead of crossing the arena.
+        let movement = arena_mode.offset(previous.translation.truncate(), current.translation.truncate());
+
+        if movement.length() > TELEPORT_DISTANCE {
+            *transform = current;
+            return;
+        }
+
+        let translation = current.translation - (movement * (1.0 - alpha)).extend(0.0);
+
+        *transform = Transform {
+            translation: arena_mode.wrap(translation),
+            rotation: previous.rotation.slerp(current.rotation, alpha),
+            scale: current.scale,
+        };
+    });
+}
@@ -0,0 +1,238 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::arena::{ArenaMode, ARENA_SIZE};
+use super::collision::Collider;
+
+/// Divides the classic arena evenly, so cells line up across the wrap seam.
+const CELL_SIZE: f32 = 80.0;
+
+/// Uniform grid of every collider, rebuilt each frame, used to find the few entities near
+/// a position instead of testing every pair.
+pub struct SpatialHash {
+    /// Indices into `entities` of everything touching a cell.
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    entities: Vec<Entity>,
+    arena_mode: ArenaMode,
+}
+
+impl SpatialHash {
+    pub fn new(arena_mode: ArenaMode) -> Self {
+        SpatialHash {
+            cells: HashMap::default(),
+            entities: Vec::new(),
+            arena_mode,
+        }
+    }
+
+    pub fn clear(&mut self, arena_mode: ArenaMode) {
+        self.cells.clear();
+        self.entities.clear();
+        self.arena_mode = arena_mode;
+    }
+
+    /// Adds an entity to every cell its bounding circle touches.
+    pub fn insert(&mut self, entity: Entity, position: Vec2, radius: f32) {
+        let index = self.entities.len();
+        self.entities.push(entity);
+
+        for cell in self.covered_cells(position, radius) {
+            self.cells.entry(cell).or_insert_with(Vec::new).push(index);
+        }
+    }
+
+    /// Entities whose cells touch the circle at `position`, each one once and in the order they
+    /// were inserted. These are only candidates, the caller still has to test the actual shapes.
+    ///
+    /// The order does not depend on the entity ids, those also change with menus and effects
+    /// spawned outside the simulation, so a replay resolves collisions the same way.
+    pub fn nearby(&self, position: Vec2, radius: f32) -> Vec<Entity> {
+
+        let mut indices: Vec<usize> = self
+            .covered_cells(position, radius)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect();
+
+        indices.sort_unstable();
+        indices.dedup();
+
+        indices.into_iter().map(|index| self.entities[index]).collect()
+    }
+
+    /// Like [`SpatialHash::nearby`] for a circle that moved by `displacement` to `position`.
+    pub fn nearby_swept(&self, position: Vec2, radius: f32, displacement: Vec2) -> Vec<Entity> {
+        self.nearby(position - displacement / 2.0, radius + displacement.length() / 2.0)
+    }
+
+    fn covered_cells(&self, position: Vec2, radius: f32) -> impl Iterator<Item = (i32, i32)> {
+        let min = ((position - Vec2::splat(radius)) / CELL_SIZE).floor();
+        let max = ((position + Vec2::splat(radius)) / CELL_SIZE).floor();
+        let arena_mode = self.arena_mode;
+
+        (min.x as i32..=max.x as i32)
+            .flat_map(move |x| (min.y as i32..=max.y as i32).map(move |y| wrap_cell(arena_mode, (x, y))))
+    }
+}
+
+pub fn spatial_hash_setup(mut commands: Commands, arena_mode: Res<ArenaMode>) {
+    commands.insert_resource(SpatialHash::new(*arena_mode));
+}
+
+pub fn remove_spatial_hash(mut commands: Commands) {
+    commands.remove_resource::<SpatialHash>();
+}
+
+pub fn rebuild_spatial_hash(
+    mut spatial_hash: ResMut<SpatialHash>,
+    query: Query<(Entity, &Transform, &Collider)>,
+    arena_mode: Res<ArenaMode>
+) {
+
+    spatial_hash.clear(*arena_mode);
+
+    query.for_each(|(entity, transform, collider)| {
+        spatial_hash.insert(entity, transform.translation.truncate(), collider.bounding_radius());
+    });
+}
+
+/// In the classic arena cells past an edge are the ones on the opposite side.
+fn wrap_cell(arena_mode: ArenaMode, (x, y): (i32, i32)) -> (i32, i32) {
+    match arena_mode {
+        ArenaMode::Open => (x, y),
+        ArenaMode::Wrap => {
+            let columns = (ARENA_SIZE.x / CELL_SIZE) as i32;
+            let rows = (ARENA_SIZE.y / CELL_SIZE) as i32;
+
+            (
+                (x + columns / 2).rem_euclid(columns) - columns / 2,
+                (y + rows / 2).rem_euclid(rows) - rows / 2
+            )
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+    use rand::rngs::StdRng;
+    use std::time::{Duration, Instant};
+
+    fn random_field(count: u32, arena_mode: ArenaMode) -> Vec<(Entity, Transform, Collider)> {
+        let mut rng = StdRng::seed_from_u64(7);
+        let extent = match arena_mode {
+            ArenaMode::Open => Vec2::splat(2000.0),
+            ArenaMode::Wrap => ARENA_SIZE,
+        };
+
+        (0..count)
+            .map(|index| {
+                let translation = Vec3::new(
+                    rng.gen_range(-extent.x / 2.0..extent.x / 2.0),
+                    rng.gen_range(-extent.y / 2.0..extent.y / 2.0),
+                    0.0
+                );
+                (Entity::from_raw(index), Transform::from_translation(translation), Collider::circle(rng.gen_range(4.0..24.0)))
+            })
+            .collect()
+    }
+
+    fn brute_force_pairs(field: &[(Entity, Transform, Collider)], arena_mode: ArenaMode) -> Vec<(Entity, Entity)> {
+        let mut pairs = Vec::new();
+
+        for (index, (entity_a, transform_a, collider_a)) in field.iter().enumerate() {
+            for (entity_b, transform_b, collider_b) in &field[index + 1..] {
+                if collider_a.overlaps(transform_a, collider_b, transform_b, &arena_mode) {
+                    pairs.push((*entity_a, *entity_b));
+                }
+            }
+        }
+
+        pairs
+    }
+
+    fn spatial_hash_pairs(field: &[(Entity, Transform, Collider)], arena_mode: ArenaMode) -> Vec<(Entity, Entity)> {
+        let mut spatial_hash = SpatialHash::new(arena_mode);
+        let mut pairs = Vec::new();
+
+        for (entity, transform, collider) in field {
+            spatial_hash.insert(*entity, transform.translation.truncate(), collider.bounding_radius());
+        }
+
+        for (entity_a, transform_a, collider_a) in field {
+            for entity_b in spatial_hash.nearby(transform_a.translation.truncate(), collider_a.bounding_radius()) {
+                if entity_b <= *entity_a {
+                    continue;
+                }
+
+                let (_, transform_b, collider_b) = &field[entity_b.id() as usize];
+                if collider_a.overlaps(transform_a, collider_b, transform_b, &arena_mode) {
+                    pairs.push((*entity_a, entity_b));
+                }
+            }
+        }
+
+        pairs
+    }
+
+    #[test]
+    fn finds_the_same_pairs_as_brute_force() {
+        for arena_mode in [ArenaMode::Open, ArenaMode::Wrap] {
+            let field = random_field(400, arena_mode);
+
+            let expected = brute_force_pairs(&field, arena_mode);
+            assert!(!expected.is_empty());
+            assert_eq!(spatial_hash_pairs(&field, arena_mode), expected);
+        }
+    }
+
+    #[test]
+    fn finds_neighbors_across_the_wrap_seam() {
+        let mut spatial_hash = SpatialHash::new(ArenaMode::Wrap);
+        let entity = Entity::from_raw(1);
+        spatial_hash.insert(entity, Vec2::new(-315.0, 235.0), 8.0);
+
+        assert_eq!(spatial_hash.nearby(Vec2::new(315.0, -235.0), 8.0), vec![entity]);
+        assert!(spatial_hash.nearby(Vec2::new(0.0, 0.0), 8.0).is_empty());
+    }
+
+    #[test]
+    fn finds_the_same_pairs_as_brute_force_in_a_crowded_field() {
+        let field = random_field(2000, ArenaMode::Open);
+
+        assert_eq!(spatial_hash_pairs(&field, ArenaMode::Open), brute_force_pairs(&field, ArenaMode::Open));
+    }
+
+    /// Fastest of a few runs, so a single slow run does not decide the comparison.
+    fn fastest_run<T>(mut run: impl FnMut() -> T) -> (T, Duration) {
+        let mut fastest = Duration::MAX;
+        let mut result = None;
+
+        for _ in 0..5 {
+            let start = Instant::now();
+            result = Some(run());
+            fastest = fastest.min(start.elapsed());
+        }
+
+        (result.unwrap(), fastest)
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture` to compare both approaches.
+    #[test]
+    #[ignore]
+    fn benchmark_against_brute_force() {
+        for count in [1000, 2000, 4000] {
+            let field = random_field(count, ArenaMode::Open);
+
+            let (brute_force, brute_force_time) = fastest_run(|| brute_force_pairs(&field, ArenaMode::Open));
+            let (grid, grid_time) = fastest_run(|| spatial_hash_pairs(&field, ArenaMode::Open));
+
+            println!("{} entities: brute force {:?}, spatial hash {:?}", count, brute_force_time, grid_time);
+
+            assert_eq!(grid, brute_force);
+            assert!(grid_time < brute_force_time);
+        }
+    }
+}
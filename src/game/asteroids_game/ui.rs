@@ -1,9 +1,162 @@
 use bevy::prelude::*;
+use bevy::render::camera::CameraProjection;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::Duration;
+
+use super::arena::ArenaMode;
+use super::asteroids::{Asteroid, AsteroidsStats};
+use super::collision::Collider;
+use super::hyperspace::Hyperspace;
+use super::player::{self, ActiveShip, MatchMode, Player, PlayerCamera, PlayerLives};
+use super::powerups::{ActivePowerUps, PowerUp};
+use super::saucers::Saucer;
+use super::versus::Versus;
+use super::waves::Waves;
+
+const HUD_FONT_SIZE: f32 = 35.0;
+const HUD_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+const PULSE_TIME: Duration = Duration::from_millis(300);
+const PULSE_SCALE: f32 = 0.4;
+const RADAR_SIZE: f32 = 160.0;
+const RADAR_MARGIN: f32 = 20.0;
+const BLIP_SIZE: f32 = 4.0;
+/// Blips are made once with the HUD, anything past this many is left off the radar.
+const BLIP_POOL_SIZE: usize = 64;
+/// Blips at the edge of the radar are this faint, the nearest ones fully opaque.
+const BLIP_MIN_ALPHA: f32 = 0.25;
+const ASTEROID_BLIP: Color = Color::rgb(0.7, 0.6, 0.5);
+const SAUCER_BLIP: Color = Color::rgb(1.0, 0.3, 0.3);
+const POWERUP_BLIP: Color = Color::rgb(0.3, 1.0, 0.5);
+/// The ranges picked from in the settings, `radar.ron` may hold any other.
+const RADAR_RANGES: [f32; 3] = [400.0, 600.0, 900.0];
+const RADAR_FILE: &str = "radar.ron";
+const THREAT_POOL_SIZE: usize = 8;
+/// Asteroids further than this many seconds from hitting a ship are not pointed out.
+const THREAT_HORIZON: f32 = 4.0;
+/// Room between the arrows and the edge of the window, in pixels.
+const THREAT_EDGE_MARGIN: f32 = 30.0;
+const THREAT_MIN_SIZE: f32 = 16.0;
+const THREAT_MAX_SIZE: f32 = 36.0;
 
 #[derive(Component)]
 pub struct UiElement;
 
-pub fn spawn_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
+#[derive(Component)]
+pub struct ScoreText {
+    player: usize,
+}
+
+#[derive(Component)]
+pub struct LivesText {
+    player: usize,
+}
+
+#[derive(Component)]
+pub struct WaveText;
+
+#[derive(Component)]
+pub struct TimerText;
+
+#[derive(Component)]
+pub struct PowerUpText {
+    player: usize,
+}
+
+#[derive(Component)]
+pub struct HyperspaceText {
+    player: usize,
+}
+
+#[derive(Component)]
+pub struct WaveBanner;
+
+/// One of the reused dots on the radar.
+#[derive(Component)]
+pub struct RadarBlip;
+
+/// The dot in the middle of the radar, in the color of the ship it is centered on.
+#[derive(Component)]
+pub struct RadarCenter;
+
+/// One of the reused arrows pointing at asteroids about to hit a ship from off screen.
+#[derive(Component)]
+pub struct ThreatArrow;
+
+/// How far from the ship the radar reaches, in arena units. Loaded from `radar.ron` when it exists.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RadarRange(pub f32);
+
+impl Default for RadarRange {
+    fn default() -> Self {
+        RadarRange(RADAR_RANGES[1])
+    }
+}
+
+impl RadarRange {
+    /// Falls back to the default range when there is no radar file or it cannot be read.
+    pub fn load() -> Self {
+        match fs::read_to_string(RADAR_FILE) {
+            Ok(text) => RadarRange::from_ron(&text).unwrap_or_else(|error| {
+                warn!("Ignoring {}: {}", RADAR_FILE, error);
+                RadarRange::default()
+            }),
+            Err(_) => RadarRange::default(),
+        }
+    }
+
+    /// Writes the range to `radar.ron`, so it is used again on the next start.
+    pub fn save(&self) {
+        let result = ron::to_string(self)
+            .map_err(|error| error.to_string())
+            .and_then(|text| fs::write(RADAR_FILE, text).map_err(|error| error.to_string()));
+
+        if let Err(error) = result {
+            warn!("Could not save {}: {}", RADAR_FILE, error);
+        }
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, String> {
+        let range: RadarRange = ron::from_str(text).map_err(|error| error.to_string())?;
+
+        if range.0 > 0.0 {
+            Ok(range)
+        } else {
+            Err(format!("the range {} is not positive", range.0))
+        }
+    }
+
+    /// The next longer of the ranges in the settings, the shortest again after the longest.
+    pub fn next(&self) -> RadarRange {
+        RadarRange(RADAR_RANGES.iter().copied().find(|range| *range > self.0).unwrap_or(RADAR_RANGES[0]))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match RADAR_RANGES.iter().position(|range| *range == self.0) {
+            Some(0) => "Short",
+            Some(1) => "Medium",
+            Some(2) => "Long",
+            _ => "Custom",
+        }
+    }
+}
+
+/// Briefly enlarges a HUD text whenever its value changes.
+#[derive(Component)]
+pub struct HudPulse {
+    timer: Timer,
+}
+
+impl HudPulse {
+    fn new() -> Self {
+        let mut timer = Timer::new(PULSE_TIME, false);
+        timer.tick(PULSE_TIME);
+
+        HudPulse { timer }
+    }
+}
+
+pub fn spawn_ui(mut commands: Commands, asset_server: Res<AssetServer>, match_mode: Res<MatchMode>) {
 
     let font_handle = asset_server.load("fonts/Regular.ttf");
 
@@ -15,6 +168,7 @@ pub fn spawn_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
         .spawn_bundle(NodeBundle {
             style: Style {
                 size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::SpaceBetween,
                 ..Default::default()
             },
             color: Color::NONE.into(),
@@ -34,21 +188,138 @@ pub fn spawn_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
                     ..Default::default()
                 })
                 .with_children(|parent| {
+                    let color = player::player_color(0);
+
                     parent
-                        .spawn_bundle(TextBundle {
-                            text: Text::with_section(
-                                "Score: 0",
-                                TextStyle {
-                                    font: font_handle.clone(),
-                                    font_size: 35.0,
-                                    color: Color::rgb(0.9, 0.9, 0.9)
-                                },
-                                Default::default()
-                            ),
-                            ..Default::default()
-                        });
+                        .spawn_bundle(hud_text("Score: 0", font_handle.clone(), color))
+                        .insert(ScoreText { player: 0 })
+                        .insert(HudPulse::new());
+                    parent
+                        .spawn_bundle(hud_text("Lives: 0", font_handle.clone(), color))
+                        .insert(LivesText { player: 0 });
+                    parent
+                        .spawn_bundle(hud_text("Wave: 1", font_handle.clone(), HUD_COLOR))
+                        .insert(WaveText)
+                        .insert(HudPulse::new());
+                    parent
+                        .spawn_bundle(hud_text("Time: 0:00", font_handle.clone(), HUD_COLOR))
+                        .insert(TimerText);
+                    parent
+                        .spawn_bundle(hud_text("Hyperspace: ready", font_handle.clone(), color))
+                        .insert(HyperspaceText { player: 0 });
+                    parent
+                        .spawn_bundle(hud_text("", font_handle.clone(), color))
+                        .insert(PowerUpText { player: 0 });
                 });
+
+            // The other players get their own column on the right, in the color of their ship.
+            for index in 1..match_mode.player_count() {
+                let color = player::player_color(index);
+
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(30.0), Val::Percent(100.0)),
+                            flex_direction: FlexDirection::ColumnReverse,
+                            align_items: AlignItems::FlexEnd,
+                            ..Default::default()
+                        },
+                        color: Color::NONE.into(),
+                        ..Default::default()
+                    })
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(hud_text("Score: 0", font_handle.clone(), color))
+                            .insert(ScoreText { player: index })
+                            .insert(HudPulse::new());
+                        parent
+                            .spawn_bundle(hud_text("Lives: 0", font_handle.clone(), color))
+                            .insert(LivesText { player: index });
+                        parent
+                            .spawn_bundle(hud_text("Hyperspace: ready", font_handle.clone(), color))
+                            .insert(HyperspaceText { player: index });
+                        parent
+                            .spawn_bundle(hud_text("", font_handle.clone(), color))
+                            .insert(PowerUpText { player: index });
+                    });
+            }
+        });
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .insert(UiElement)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "Wave 1",
+                        TextStyle {
+                            font: font_handle.clone(),
+                            font_size: 80.0,
+                            color: HUD_COLOR
+                        },
+                        Default::default()
+                    ),
+                    ..Default::default()
+                })
+                .insert(WaveBanner);
+        });
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(RADAR_SIZE), Val::Px(RADAR_SIZE)),
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(RADAR_MARGIN),
+                    bottom: Val::Px(RADAR_MARGIN),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            color: Color::rgba(0.1, 0.2, 0.1, 0.5).into(),
+            ..Default::default()
+        })
+        .insert(UiElement)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(radar_dot(Vec2::splat(RADAR_SIZE / 2.0), player::player_color(0)))
+                .insert(RadarCenter);
+
+            for _ in 0..BLIP_POOL_SIZE {
+                parent
+                    .spawn_bundle(radar_dot(Vec2::ZERO, Color::NONE))
+                    .insert(RadarBlip);
+            }
         });
+
+    let arrow_handle = asset_server.load("images/arrow.png");
+
+    for _ in 0..THREAT_POOL_SIZE {
+        commands
+            .spawn_bundle(ImageBundle {
+                style: Style {
+                    size: Size::new(Val::Px(THREAT_MIN_SIZE), Val::Px(THREAT_MIN_SIZE)),
+                    position_type: PositionType::Absolute,
+                    ..Default::default()
+                },
+                image: arrow_handle.clone().into(),
+                visibility: Visibility { is_visible: false },
+                ..Default::default()
+            })
+            .insert(UiElement)
+            .insert(ThreatArrow);
+    }
 }
 
 pub fn remove_ui(mut commands: Commands, query: Query<Entity, With<UiElement>>) {
@@ -56,3 +327,429 @@ pub fn remove_ui(mut commands: Commands, query: Query<Entity, With<UiElement>>)
         commands.entity(entity).despawn_recursive();
     });
 }
+
+/// Shows the score of every player, or the rounds they won in versus.
+pub fn update_score(
+    asteroids_stats: Res<AsteroidsStats>,
+    versus: Option<Res<Versus>>,
+    mut query: Query<(&mut Text, &mut HudPulse, &ScoreText)>
+) {
+    query.for_each_mut(|(mut text, mut pulse, score_text)| {
+        let value = match versus.as_ref() {
+            Some(versus) => format!("Rounds won: {}", versus.wins(score_text.player)),
+            None => format!("Score: {}", asteroids_stats.player_score(score_text.player)),
+        };
+
+        set_text(&mut text, value, Some(&mut pulse));
+    });
+}
+
+pub fn update_lives(
+    player_query: Query<(&Player, &PlayerLives)>,
+    versus: Option<Res<Versus>>,
+    mut query: Query<(&mut Text, &LivesText)>
+) {
+    query.for_each_mut(|(mut text, lives_text)| {
+        // A versus round is over with the first hit, lives do not matter there.
+        if versus.is_some() {
+            set_text(&mut text, String::new(), None);
+        } else if let Some((_, player_lives)) = player_query.iter().find(|(player, _)| player.index() == lives_text.player) {
+            set_text(&mut text, format!("Lives: {}", player_lives.lives()), None);
+        }
+    });
+}
+
+pub fn update_wave(
+    waves: Res<Waves>,
+    versus: Option<Res<Versus>>,
+    mut query: Query<(&mut Text, &mut HudPulse), With<WaveText>>
+) {
+    let value = match versus {
+        Some(versus) => format!("Round: {}", versus.round()),
+        None => format!("Wave: {}", waves.number()),
+    };
+
+    query.for_each_mut(|(mut text, mut pulse)| {
+        set_text(&mut text, value.clone(), Some(&mut pulse));
+    });
+}
+
+/// Announces the next wave during the breather, in versus the countdown and the end of a round.
+pub fn update_wave_banner(
+    waves: Res<Waves>,
+    versus: Option<Res<Versus>>,
+    mut query: Query<(&mut Text, &mut Visibility), With<WaveBanner>>
+) {
+    let banner = match versus {
+        Some(versus) => versus.banner(),
+        None => waves.in_breather().then(|| format!("Wave {}", waves.number())),
+    };
+
+    query.for_each_mut(|(mut text, mut visibility)| {
+        if visibility.is_visible != banner.is_some() {
+            visibility.is_visible = banner.is_some();
+        }
+
+        if let Some(banner) = &banner {
+            set_text(&mut text, banner.clone(), None);
+        }
+    });
+}
+
+pub fn update_timer(asteroids_stats: Res<AsteroidsStats>, mut query: Query<&mut Text, With<TimerText>>) {
+    let seconds = asteroids_stats.elapsed().as_secs();
+
+    query.for_each_mut(|mut text| {
+        set_text(&mut text, format!("Time: {}:{:02}", seconds / 60, seconds % 60), None);
+    });
+}
+
+pub fn update_powerups(
+    player_query: Query<(&Player, &ActivePowerUps)>,
+    mut query: Query<(&mut Text, &PowerUpText)>
+) {
+    query.for_each_mut(|(mut text, power_up_text)| {
+        let value = match player_query.iter().find(|(player, _)| player.index() == power_up_text.player) {
+            Some((_, power_ups)) => power_ups
+                .remaining()
+                .iter()
+                .map(|(kind, remaining)| format!("{} {}s", kind.name(), remaining.as_secs() + 1))
+                .collect::<Vec<_>>()
+                .join("  "),
+            None => String::new(),
+        };
+
+        set_text(&mut text, value, None);
+    });
+}
+
+pub fn update_hyperspace(
+    player_query: Query<(&Player, &Hyperspace)>,
+    mut query: Query<(&mut Text, &HyperspaceText)>
+) {
+    query.for_each_mut(|(mut text, hyperspace_text)| {
+        let remaining = match player_query.iter().find(|(player, _)| player.index() == hyperspace_text.player) {
+            Some((_, hyperspace)) => hyperspace.remaining_cooldown(),
+            None => return,
+        };
+
+        let value = if remaining.is_zero() {
+            "Hyperspace: ready".to_string()
+        } else {
+            format!("Hyperspace: {}s", remaining.as_secs() + 1)
+        };
+
+        set_text(&mut text, value, None);
+    });
+}
+
+/// Plots what is around the first ship in the arena, the closest contacts first when there are
+/// more than blips. The blips are only moved and recolored, never spawned.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn update_radar(
+    player_query: Query<(&Transform, &Player, &PlayerLives)>,
+    asteroid_query: Query<&Transform, With<Asteroid>>,
+    saucer_query: Query<&Transform, With<Saucer>>,
+    powerup_query: Query<&Transform, With<PowerUp>>,
+    mut blip_query: Query<(&mut Style, &mut UiColor, &mut Visibility), With<RadarBlip>>,
+    mut center_query: Query<(&mut UiColor, &mut Visibility), (With<RadarCenter>, Without<RadarBlip>)>,
+    arena_mode: Res<ArenaMode>,
+    range: Res<RadarRange>
+) {
+
+    // Ships waiting to respawn or out of lives are not in the arena, the radar follows the first
+    // ship that is and shows the others.
+    let mut ships: Vec<(&Transform, &Player)> = player_query
+        .iter()
+        .filter(|(_, _, player_lives)| !player_lives.destroyed())
+        .map(|(transform, player, _)| (transform, player))
+        .collect();
+    ships.sort_unstable_by_key(|(_, player)| player.index());
+
+    center_query.for_each_mut(|(mut color, mut visibility)| {
+        match ships.first() {
+            Some((_, player)) => {
+                *color = player.color().into();
+
+                if !visibility.is_visible {
+                    visibility.is_visible = true;
+                }
+            },
+            None => {
+                if visibility.is_visible {
+                    visibility.is_visible = false;
+                }
+            },
+        }
+    });
+
+    let mut blips: Vec<(Vec2, f32, Color)> = match ships.split_first() {
+        Some(((center_transform, _), other_ships)) => {
+            let center = center_transform.translation.truncate();
+
+            asteroid_query
+                .iter()
+                .map(|transform| (transform, ASTEROID_BLIP))
+                .chain(saucer_query.iter().map(|transform| (transform, SAUCER_BLIP)))
+                .chain(powerup_query.iter().map(|transform| (transform, POWERUP_BLIP)))
+                .chain(other_ships.iter().map(|(transform, player)| (*transform, player.color())))
+                .filter_map(|(transform, mut color)| {
+                    let offset = arena_mode.offset(center, transform.translation.truncate());
+                    let (position, alpha) = blip_position(offset, range.0)?;
+
+                    Some((position, offset.length(), *color.set_a(alpha)))
+                })
+                .collect()
+        },
+        // Nothing to center on, the radar stays empty.
+        None => Vec::new(),
+    };
+    blips.sort_unstable_by(|(_, a, _), (_, b, _)| a.total_cmp(b));
+
+    let mut blips = blips.into_iter();
+
+    blip_query.for_each_mut(|(mut style, mut color, mut visibility)| {
+        match blips.next() {
+            Some((position, _, blip_color)) => {
+                style.position.left = Val::Px(position.x - BLIP_SIZE / 2.0);
+                style.position.bottom = Val::Px(position.y - BLIP_SIZE / 2.0);
+                *color = blip_color.into();
+
+                if !visibility.is_visible {
+                    visibility.is_visible = true;
+                }
+            },
+            None => {
+                if visibility.is_visible {
+                    visibility.is_visible = false;
+                }
+            },
+        }
+    });
+}
+
+/// Points from the edge of the window at the asteroids that will hit a ship soon, growing and
+/// turning red the closer the impact is.
+#[allow(clippy::type_complexity)]
+pub fn update_threat_arrows(
+    player_query: Query<(&Transform, &Player, &Collider), ActiveShip>,
+    asteroid_query: Query<(&Transform, &Asteroid, &Collider)>,
+    camera_query: Query<(&Transform, &OrthographicProjection), (With<PlayerCamera>, Without<ThreatArrow>)>,
+    mut arrow_query: Query<(&mut Style, &mut UiColor, &mut Visibility, &mut Transform), (With<ThreatArrow>, Without<Player>, Without<Asteroid>)>,
+    arena_mode: Res<ArenaMode>,
+    windows: Res<Windows>
+) {
+
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+
+    let (camera_transform, projection) = match camera_query.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+
+    let world_to_ndc = projection.get_projection_matrix() * camera_transform.compute_matrix().inverse();
+    let half_window = Vec2::new(window.width(), window.height()) / 2.0;
+
+    let mut threats: Vec<(Vec2, f32)> = asteroid_query
+        .iter()
+        .filter_map(|(asteroid_transform, asteroid, asteroid_collider)| {
+            // The soonest impact with any of the ships.
+            let (apparent_position, impact) = player_query
+                .iter()
+                .filter_map(|(player_transform, player, player_collider)| {
+                    let ship_translation = player_transform.translation.truncate();
+                    let offset = arena_mode.offset(ship_translation, asteroid_transform.translation.truncate());
+                    let radius = asteroid_collider.bounding_radius() + player_collider.bounding_radius();
+
+                    time_to_impact(offset, asteroid.velocity() - player.velocity(), radius).map(|impact| (ship_translation + offset, impact))
+                })
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+
+            if impact > THREAT_HORIZON {
+                return None;
+            }
+
+            // The asteroid is drawn where the ship sees it, on its side of a wrapping arena edge.
+            let ndc = world_to_ndc.project_point3(apparent_position.extend(0.0)).truncate();
+            let on_screen = ndc.abs().cmple(Vec2::ONE).all();
+
+            (!on_screen).then(|| (ndc * half_window, impact))
+        })
+        .collect();
+    threats.sort_unstable_by(|(_, a), (_, b)| a.total_cmp(b));
+
+    let mut threats = threats.into_iter();
+
+    arrow_query.for_each_mut(|(mut style, mut color, mut visibility, mut transform)| {
+        let (direction, impact) = match threats.next() {
+            Some(threat) => threat,
+            None => {
+                if visibility.is_visible {
+                    visibility.is_visible = false;
+                }
+                return;
+            },
+        };
+
+        let urgency = 1.0 - impact / THREAT_HORIZON;
+        let size = THREAT_MIN_SIZE + (THREAT_MAX_SIZE - THREAT_MIN_SIZE) * urgency;
+
+        // Pulled in along the line from the middle of the window until it touches the margin.
+        let reach = half_window - Vec2::splat(THREAT_EDGE_MARGIN);
+        let edge = direction * (reach / direction.abs()).min_element();
+        let position = half_window + edge;
+
+        style.size = Size::new(Val::Px(size), Val::Px(size));
+        style.position.left = Val::Px(position.x - size / 2.0);
+        style.position.bottom = Val::Px(position.y - size / 2.0);
+        *color = Color::rgba(1.0, 1.0 - 0.8 * urgency, 0.2, 0.6 + 0.4 * urgency).into();
+        // The layout only places the arrows, their rotation is left alone.
+        transform.rotation = Quat::from_rotation_z((-direction.x).atan2(direction.y));
+
+        if !visibility.is_visible {
+            visibility.is_visible = true;
+        }
+    });
+}
+
+pub fn animate_pulse(mut query: Query<(&mut Text, &mut HudPulse)>, time: Res<Time>) {
+    query.for_each_mut(|(mut text, mut pulse)| {
+        if pulse.timer.finished() {
+            return;
+        }
+
+        pulse.timer.tick(time.delta());
+
+        let remaining = 1.0 - pulse.timer.percent();
+        text.sections[0].style.font_size = HUD_FONT_SIZE * (1.0 + PULSE_SCALE * remaining);
+    });
+}
+
+/// Only touches the text when the value differs, so unchanged elements are not laid out again.
+fn set_text(text: &mut Mut<Text>, value: String, pulse: Option<&mut HudPulse>) {
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+
+        if let Some(pulse) = pulse {
+            pulse.timer.reset();
+        }
+    }
+}
+
+/// Seconds until something at `offset` from a ship, moving at `relative_velocity` to it, comes
+/// within `radius` of it. `None` when its closest approach misses or lies in the past.
+fn time_to_impact(offset: Vec2, relative_velocity: Vec2, radius: f32) -> Option<f32> {
+    let closing = offset.dot(relative_velocity);
+    let distance_squared = offset.length_squared() - radius * radius;
+
+    if distance_squared <= 0.0 {
+        return Some(0.0);
+    }
+
+    let speed_squared = relative_velocity.length_squared();
+
+    if closing >= 0.0 || speed_squared == 0.0 {
+        return None;
+    }
+
+    let discriminant = closing * closing - speed_squared * distance_squared;
+
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    Some((-closing - discriminant.sqrt()) / speed_squared)
+}
+
+/// Where `offset` from the ship lands on the radar and how opaque its blip is, `None` when out of range.
+fn blip_position(offset: Vec2, range: f32) -> Option<(Vec2, f32)> {
+    let distance = offset.length() / range;
+
+    if distance > 1.0 {
+        return None;
+    }
+
+    let position = (offset / range + Vec2::ONE) * RADAR_SIZE / 2.0;
+    let alpha = 1.0 - distance * (1.0 - BLIP_MIN_ALPHA);
+
+    Some((position, alpha))
+}
+
+fn radar_dot(position: Vec2, color: Color) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            size: Size::new(Val::Px(BLIP_SIZE), Val::Px(BLIP_SIZE)),
+            position_type: PositionType::Absolute,
+            position: Rect {
+                left: Val::Px(position.x - BLIP_SIZE / 2.0),
+                bottom: Val::Px(position.y - BLIP_SIZE / 2.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        color: color.into(),
+        ..Default::default()
+    }
+}
+
+fn hud_text(value: &str, font: Handle<Font>, color: Color) -> TextBundle {
+    TextBundle {
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font,
+                font_size: HUD_FONT_SIZE,
+                color
+            },
+            Default::default()
+        ),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_asteroids_on_a_collision_course_are_threats() {
+        // Head on at 50 units per second, touching after 160 of the 200 units.
+        let impact = time_to_impact(Vec2::new(200.0, 0.0), Vec2::new(-50.0, 0.0), 40.0).unwrap();
+        assert!((impact - 3.2).abs() < 1e-4);
+
+        // Passing 60 units wide of the ship, or already flying away from it.
+        assert_eq!(time_to_impact(Vec2::new(200.0, 60.0), Vec2::new(-50.0, 0.0), 40.0), None);
+        assert_eq!(time_to_impact(Vec2::new(200.0, 0.0), Vec2::new(50.0, 0.0), 40.0), None);
+
+        // Grazing within the radius counts.
+        assert!(time_to_impact(Vec2::new(200.0, 30.0), Vec2::new(-50.0, 0.0), 40.0).is_some());
+    }
+
+    #[test]
+    fn radar_ranges_cycle_and_load_from_ron() {
+        let range = RadarRange::default();
+        assert_eq!(range.name(), "Medium");
+        assert_eq!(range.next().next(), RadarRange(RADAR_RANGES[0]));
+
+        assert_eq!(RadarRange::from_ron(&ron::to_string(&RadarRange(750.0)).unwrap()), Ok(RadarRange(750.0)));
+        assert_eq!(RadarRange(750.0).name(), "Custom");
+        assert_eq!(RadarRange(750.0).next(), RadarRange(RADAR_RANGES[2]));
+        assert!(RadarRange::from_ron("(-5.0)").is_err());
+    }
+
+    #[test]
+    fn blips_fade_towards_the_edge_of_the_radar() {
+        let (center, center_alpha) = blip_position(Vec2::ZERO, 500.0).unwrap();
+        assert_eq!(center, Vec2::splat(RADAR_SIZE / 2.0));
+        assert_eq!(center_alpha, 1.0);
+
+        let (edge, edge_alpha) = blip_position(Vec2::new(-500.0, 0.0), 500.0).unwrap();
+        assert_eq!(edge, Vec2::new(0.0, RADAR_SIZE / 2.0));
+        assert_eq!(edge_alpha, BLIP_MIN_ALPHA);
+
+        assert!(blip_position(Vec2::new(400.0, 400.0), 500.0).is_none());
+    }
+}
@@ -0,0 +1,253 @@
+use bevy::prelude::*;
+use std::time::Duration;
+
+use crate::game::GameState;
+use crate::game::game_over::GameResults;
+use super::asteroids::{self, Asteroid, AsteroidsAtlas, AsteroidsStats};
+use super::player::{self, LaserBullet, MatchMode, Player, PlayerLives, MAX_PLAYERS};
+use super::powerups::ActivePowerUps;
+use super::replay::TickInputs;
+use super::rng::GameRng;
+use super::simulation::FixedTime;
+
+/// A match is best of this many rounds, drawn rounds do not count towards it.
+const ROUNDS: u32 = 5;
+const COUNTDOWN_TIME: Duration = Duration::from_secs(3);
+/// How long the result of a round stays up before the next one starts.
+const ROUND_OVER_TIME: Duration = Duration::from_secs(2);
+const OBSTACLE_COUNT: u32 = 5;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RoundPhase {
+    /// Before the first round, the arena is set up in the first step.
+    Setup,
+    Countdown,
+    Fighting,
+    /// `winner` is `None` when both ships went down in the same step.
+    Over { winner: Option<usize> },
+}
+
+/// Round scores of a head-to-head match, only present in [`MatchMode::Versus`].
+pub struct Versus {
+    round: u32,
+    wins: [u32; MAX_PLAYERS],
+    phase: RoundPhase,
+    timer: Timer,
+}
+
+impl Versus {
+    pub fn round(&self) -> u32 {
+        self.round
+    }
+
+    pub fn wins(&self, player: usize) -> u32 {
+        self.wins[player]
+    }
+
+    pub fn counting_down(&self) -> bool {
+        self.phase == RoundPhase::Countdown
+    }
+
+    pub fn fighting(&self) -> bool {
+        self.phase == RoundPhase::Fighting
+    }
+
+    /// Text shown across the arena while the ships are not fighting.
+    pub fn banner(&self) -> Option<String> {
+        match self.phase {
+            RoundPhase::Setup | RoundPhase::Fighting => None,
+            RoundPhase::Countdown => {
+                let remaining = self.timer.duration() - self.timer.elapsed();
+                Some(format!("Round {}\n{}", self.round, remaining.as_secs_f32().ceil()))
+            },
+            RoundPhase::Over { winner: Some(winner) } => Some(format!("Player {} wins the round", winner + 1)),
+            RoundPhase::Over { winner: None } => Some("Draw".to_string()),
+        }
+    }
+
+    fn match_winner(&self) -> Option<usize> {
+        self.wins.iter().position(|wins| *wins > ROUNDS / 2)
+    }
+
+    fn start_round(&mut self) {
+        self.round += 1;
+        self.phase = RoundPhase::Countdown;
+        self.timer = Timer::new(COUNTDOWN_TIME, false);
+    }
+
+    fn end_round(&mut self, winner: Option<usize>) {
+        if let Some(winner) = winner {
+            self.wins[winner] += 1;
+        }
+
+        self.phase = RoundPhase::Over { winner };
+        self.timer = Timer::new(ROUND_OVER_TIME, false);
+    }
+}
+
+pub fn versus_setup(mut commands: Commands, match_mode: Res<MatchMode>) {
+    if *match_mode != MatchMode::Versus {
+        return;
+    }
+
+    commands.insert_resource(Versus {
+        round: 0,
+        wins: [0; MAX_PLAYERS],
+        phase: RoundPhase::Setup,
+        timer: Timer::new(COUNTDOWN_TIME, false),
+    });
+}
+
+pub fn remove_versus(mut commands: Commands) {
+    commands.remove_resource::<Versus>();
+}
+
+/// Keeps the ships on their marks until the countdown is over. Runs after the inputs are read,
+/// so the replays still hold what was pressed.
+pub fn hold_ships(versus: Option<Res<Versus>>, mut inputs: ResMut<TickInputs>) {
+    if versus.is_some_and(|versus| versus.counting_down()) {
+        *inputs = TickInputs::default();
+    }
+}
+
+/// A round ends as soon as a ship is destroyed, the next one starts from a fresh arena.
+#[allow(clippy::too_many_arguments)]
+pub fn round_progress(
+    mut commands: Commands,
+    versus: Option<ResMut<Versus>>,
+    mut player_query: Query<(Entity, &mut Transform, &mut Player, &mut Visibility, &mut ActivePowerUps, &mut PlayerLives)>,
+    asteroid_query: Query<Entity, With<Asteroid>>,
+    laser_query: Query<Entity, With<LaserBullet>>,
+    asteroids_atlas: Res<AsteroidsAtlas>,
+    mut asteroid_stats: ResMut<AsteroidsStats>,
+    mut rng: ResMut<GameRng>,
+    mut game_state: ResMut<State<GameState>>,
+    time: Res<FixedTime>
+) {
+
+    let mut versus = match versus {
+        Some(versus) => versus,
+        None => return,
+    };
+
+    match versus.phase {
+        RoundPhase::Setup => {},
+        RoundPhase::Countdown => {
+            versus.timer.tick(time.delta());
+
+            if versus.timer.finished() {
+                versus.phase = RoundPhase::Fighting;
+            }
+            return;
+        },
+        RoundPhase::Fighting => {
+            let mut destroyed: Vec<usize> = player_query
+                .iter()
+                .filter(|(_, _, _, _, _, player_lives)| player_lives.destroyed())
+                .map(|(_, _, player, _, _, _)| player.index())
+                .collect();
+            destroyed.sort_unstable();
+
+            let winner = match destroyed.as_slice() {
+                [] => return,
+                [loser] => Some(1 - loser),
+                _ => None,
+            };
+
+            versus.end_round(winner);
+            return;
+        },
+        RoundPhase::Over { .. } => {
+            versus.timer.tick(time.delta());
+
+            if !versus.timer.finished() {
+                return;
+            }
+
+            if let Some(winner) = versus.match_winner() {
+                commands.insert_resource(GameResults {
+                    score: asteroid_stats.score(),
+                    player_scores: Vec::new(),
+                    destroyed: asteroid_stats.destroyed_number(),
+                    accuracy: asteroid_stats.accuracy(),
+                    time_survived: asteroid_stats.elapsed(),
+                    seed: rng.seed(),
+                    winner: Some(winner),
+                    rounds_won: versus.wins.to_vec(),
+                });
+                game_state.overwrite_push(GameState::GameOver).unwrap();
+                return;
+            }
+        },
+    }
+
+    // Every round starts from an arena cleared of the last one.
+    asteroid_query.for_each(|entity| {
+        commands.entity(entity).despawn();
+        asteroid_stats.removed();
+    });
+    laser_query.for_each(|entity| {
+        commands.entity(entity).despawn();
+    });
+
+    asteroids::spawn_obstacles(&mut commands, &asteroids_atlas, &mut asteroid_stats, &mut rng, OBSTACLE_COUNT);
+
+    player_query.for_each_mut(|(entity, mut transform, mut player, mut visibility, mut power_ups, mut player_lives)| {
+        player::reset_ship(
+            &mut commands,
+            entity,
+            &mut transform,
+            &mut player,
+            &mut visibility,
+            &mut power_ups,
+            &mut player_lives,
+            MatchMode::Versus,
+            COUNTDOWN_TIME
+        );
+    });
+
+    versus.start_round();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versus() -> Versus {
+        Versus {
+            round: 0,
+            wins: [0; MAX_PLAYERS],
+            phase: RoundPhase::Setup,
+            timer: Timer::new(COUNTDOWN_TIME, false),
+        }
+    }
+
+    #[test]
+    fn the_first_to_win_most_rounds_takes_the_match() {
+        let mut versus = versus();
+
+        for winner in [Some(0), Some(1), None, Some(1)] {
+            versus.start_round();
+            versus.end_round(winner);
+            assert_eq!(versus.match_winner(), None);
+        }
+
+        versus.start_round();
+        versus.end_round(Some(1));
+
+        assert_eq!(versus.round(), 5);
+        assert_eq!((versus.wins(0), versus.wins(1)), (1, 3));
+        assert_eq!(versus.match_winner(), Some(1));
+    }
+
+    #[test]
+    fn the_countdown_shows_the_seconds_left() {
+        let mut versus = versus();
+        versus.start_round();
+
+        assert_eq!(versus.banner().as_deref(), Some("Round 1\n3"));
+
+        versus.timer.tick(Duration::from_millis(2500));
+        assert_eq!(versus.banner().as_deref(), Some("Round 1\n1"));
+    }
+}
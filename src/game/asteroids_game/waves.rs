@@ -0,0 +1,173 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+use std::time::Duration;
+
+use super::asteroids::{AsteroidSize, AsteroidsStats};
+use super::simulation::FixedTime;
+
+const BREATHER_TIME: Duration = Duration::from_secs(3);
+const EXTRA_ASTEROIDS_PER_WAVE: u32 = 2;
+const EXTRA_SPEED_PER_WAVE: f32 = 0.05;
+
+#[derive(Clone, Copy)]
+pub struct WaveDefinition {
+    pub asteroid_count: u32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    /// Relative chances of spawning a large, medium or small asteroid.
+    pub size_weights: [u32; 3],
+    pub spawn_interval: Duration,
+}
+
+/// Waves past the end of this table repeat the last entry with more and faster asteroids.
+const WAVES: [WaveDefinition; 5] = [
+    WaveDefinition {
+        asteroid_count: 3,
+        min_speed: 30.0,
+        max_speed: 50.0,
+        size_weights: [1, 0, 0],
+        spawn_interval: Duration::from_millis(3000),
+    },
+    WaveDefinition {
+        asteroid_count: 5,
+        min_speed: 35.0,
+        max_speed: 60.0,
+        size_weights: [3, 1, 0],
+        spawn_interval: Duration::from_millis(2500),
+    },
+    WaveDefinition {
+        asteroid_count: 7,
+        min_speed: 40.0,
+        max_speed: 70.0,
+        size_weights: [3, 2, 1],
+        spawn_interval: Duration::from_millis(2000),
+    },
+    WaveDefinition {
+        asteroid_count: 9,
+        min_speed: 45.0,
+        max_speed: 80.0,
+        size_weights: [2, 2, 1],
+        spawn_interval: Duration::from_millis(1500),
+    },
+    WaveDefinition {
+        asteroid_count: 12,
+        min_speed: 50.0,
+        max_speed: 90.0,
+        size_weights: [2, 2, 2],
+        spawn_interval: Duration::from_millis(1200),
+    },
+];
+
+impl WaveDefinition {
+    pub fn random_size(&self, rng: &mut impl Rng) -> AsteroidSize {
+        let sizes = [AsteroidSize::Large, AsteroidSize::Medium, AsteroidSize::Small];
+        let total: u32 = self.size_weights.iter().sum();
+        let mut pick = rng.gen_range(0..total.max(1));
+
+        for (size, weight) in sizes.iter().zip(self.size_weights) {
+            if pick < weight {
+                return *size;
+            }
+            pick -= weight;
+        }
+
+        AsteroidSize::Large
+    }
+
+    pub fn random_speed(&self, rng: &mut impl Rng) -> f32 {
+        rng.gen_range(self.min_speed..self.max_speed)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum WavePhase {
+    Breather,
+    Spawning,
+}
+
+pub struct Waves {
+    number: u32,
+    phase: WavePhase,
+    spawned: u32,
+    breather: Timer,
+    spawn_timer: Timer,
+}
+
+impl Waves {
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    pub fn in_breather(&self) -> bool {
+        self.phase == WavePhase::Breather
+    }
+
+    pub fn definition(&self) -> WaveDefinition {
+        let index = (self.number as usize - 1).min(WAVES.len() - 1);
+        let extra_waves = (self.number as usize - 1 - index) as u32;
+        let mut definition = WAVES[index];
+
+        let speed_factor = 1.0 + extra_waves as f32 * EXTRA_SPEED_PER_WAVE;
+        definition.asteroid_count += extra_waves * EXTRA_ASTEROIDS_PER_WAVE;
+        definition.min_speed *= speed_factor;
+        definition.max_speed *= speed_factor;
+
+        definition
+    }
+
+    /// Ticks the spawn interval and returns whether the next asteroid of the wave is due.
+    pub fn spawn_due(&mut self, delta: Duration) -> bool {
+        if self.phase != WavePhase::Spawning || self.spawned >= self.definition().asteroid_count {
+            return false;
+        }
+
+        self.spawn_timer.tick(delta);
+
+        // The first asteroid of a wave appears right after the breather.
+        if self.spawned == 0 || self.spawn_timer.just_finished() {
+            self.spawned += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+pub fn waves_setup(mut commands: Commands) {
+    commands.insert_resource(Waves {
+        number: 1,
+        phase: WavePhase::Breather,
+        spawned: 0,
+        breather: Timer::new(BREATHER_TIME, false),
+        spawn_timer: Timer::new(WAVES[0].spawn_interval, true),
+    });
+}
+
+pub fn remove_waves(mut commands: Commands) {
+    commands.remove_resource::<Waves>();
+}
+
+pub fn wave_progress(mut waves: ResMut<Waves>, asteroids_stats: Res<AsteroidsStats>, time: Res<FixedTime>) {
+    match waves.phase {
+        WavePhase::Breather => {
+            waves.breather.tick(time.delta());
+
+            if waves.breather.finished() {
+                let interval = waves.definition().spawn_interval;
+
+                waves.phase = WavePhase::Spawning;
+                waves.spawned = 0;
+                waves.spawn_timer = Timer::new(interval, true);
+            }
+        },
+        WavePhase::Spawning => {
+            let all_spawned = waves.spawned >= waves.definition().asteroid_count;
+
+            if all_spawned && asteroids_stats.current_number() == 0 {
+                waves.number += 1;
+                waves.phase = WavePhase::Breather;
+                waves.breather.reset();
+            }
+        },
+    }
+}
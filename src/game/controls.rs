@@ -0,0 +1,405 @@
+use bevy::prelude::*;
+use crate::game::{button_colors, GameState};
+use crate::game::actions::{Action, ActionMap, Binding, SecondPlayerActionMap};
+
+const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+const CONFLICT_COLOR: Color = Color::rgb(0.9, 0.3, 0.3);
+const CAPTURE_COLOR: Color = Color::rgb(0.9, 0.8, 0.3);
+
+#[derive(Component)]
+struct ControlsItem;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BindingKind {
+    Key,
+    GamepadButton,
+}
+
+impl BindingKind {
+    fn matches(&self, binding: &Binding) -> bool {
+        matches!(
+            (self, binding),
+            (BindingKind::Key, Binding::Key(_)) | (BindingKind::GamepadButton, Binding::GamepadButton(_))
+        )
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+enum ControlsButton {
+    Rebind(Action, BindingKind),
+    SwitchPlayer,
+    ResetDefaults,
+    Back,
+}
+
+/// Text of a rebind button, shows the current binding or asks for a new one.
+#[derive(Component)]
+struct BindingText(Action, BindingKind);
+
+#[derive(Component)]
+struct StatusText;
+
+/// Text of the button switching between the players' bindings.
+#[derive(Component)]
+struct PlayerText;
+
+/// The binding waiting for the next key or gamepad button press.
+#[derive(Default)]
+struct Capturing(Option<(Action, BindingKind)>);
+
+/// Whose bindings the screen shows, the first or the second player's.
+#[derive(Default)]
+struct EditedPlayer(usize);
+
+impl EditedPlayer {
+    fn map<'a>(&self, action_map: &'a mut ActionMap, second_map: &'a mut SecondPlayerActionMap) -> &'a mut ActionMap {
+        match self.0 {
+            0 => action_map,
+            _ => &mut second_map.0,
+        }
+    }
+}
+
+pub(crate) struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system_set(
+                SystemSet::on_enter(GameState::Controls)
+                    .with_system(on_enter)
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Controls)
+                    .with_system(on_exit)
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Controls)
+                    .with_system(handle_buttons)
+                    // The gamepad button that starts capturing must not be captured itself.
+                    .with_system(capture_binding.before(handle_buttons))
+                    .with_system(update_texts
+                                 .after(handle_buttons)
+                                 .after(capture_binding)
+                    )
+            );
+    }
+}
+
+fn on_enter(mut commands: Commands, asset_server: Res<AssetServer>) {
+
+    let font: Handle<Font> = asset_server.load("fonts/Regular.ttf");
+
+    commands.init_resource::<Capturing>();
+    commands.init_resource::<EditedPlayer>();
+
+    commands
+        .spawn_bundle(UiCameraBundle::default())
+        .insert(ControlsItem);
+
+    // Opened on top of the settings or the pause menu, so it covers them completely.
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::FlexStart,
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            color: Color::BLACK.into(),
+            ..Default::default()
+        })
+        .insert(ControlsItem)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "Controls",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 80.0,
+                            color: TEXT_COLOR
+                        },
+                        Default::default()
+                    ),
+                    style: Style {
+                        margin: Rect {
+                            top: Val::Px(40.0),
+                            bottom: Val::Px(20.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                });
+
+            for action in Action::ALL {
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(700.0), Val::Px(50.0)),
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        color: Color::NONE.into(),
+                        ..Default::default()
+                    })
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(TextBundle {
+                                text: Text::with_section(
+                                    action.name(),
+                                    TextStyle {
+                                        font: font.clone(),
+                                        font_size: 30.0,
+                                        color: TEXT_COLOR
+                                    },
+                                    Default::default()
+                                ),
+                                style: Style {
+                                    size: Size::new(Val::Px(240.0), Val::Auto),
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            });
+
+                        spawn_button(parent, font.clone(), ControlsButton::Rebind(action, BindingKind::Key));
+                        spawn_button(parent, font.clone(), ControlsButton::Rebind(action, BindingKind::GamepadButton));
+                    });
+            }
+
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 25.0,
+                            color: TEXT_COLOR
+                        },
+                        Default::default()
+                    ),
+                    style: Style {
+                        margin: Rect::all(Val::Px(15.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(StatusText);
+
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        justify_content: JustifyContent::Center,
+                        ..Default::default()
+                    },
+                    color: Color::NONE.into(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    spawn_button(parent, font.clone(), ControlsButton::SwitchPlayer);
+                    spawn_button(parent, font.clone(), ControlsButton::ResetDefaults);
+                    spawn_button(parent, font.clone(), ControlsButton::Back);
+                });
+        });
+}
+
+fn on_exit(mut commands: Commands, query: Query<Entity, With<ControlsItem>>) {
+
+    query.for_each(|entity| {
+        commands.entity(entity).despawn_recursive();
+    });
+
+    commands.remove_resource::<Capturing>();
+    commands.remove_resource::<EditedPlayer>();
+}
+
+fn handle_buttons(
+    mut game_state: ResMut<State<GameState>>,
+    mut query: Query<(&Interaction, &mut UiColor, &ControlsButton), Changed<Interaction>>,
+    mut capturing: ResMut<Capturing>,
+    mut edited: ResMut<EditedPlayer>,
+    mut action_map: ResMut<ActionMap>,
+    mut second_map: ResMut<SecondPlayerActionMap>
+) {
+
+    query.for_each_mut(|(interaction, mut color, controls_button)| match interaction {
+        Interaction::Clicked => {
+
+            match *controls_button {
+                ControlsButton::Rebind(action, kind) => {
+                    // Clicking the binding that is waiting for input again cancels it.
+                    capturing.0 = match capturing.0 {
+                        Some(current) if current == (action, kind) => None,
+                        _ => Some((action, kind)),
+                    };
+                },
+                ControlsButton::SwitchPlayer => {
+                    capturing.0 = None;
+                    edited.0 = 1 - edited.0;
+                },
+                ControlsButton::ResetDefaults => {
+                    capturing.0 = None;
+                    let map = edited.map(&mut action_map, &mut second_map);
+                    *map = ActionMap::defaults(edited.0);
+                    map.save(edited.0);
+                },
+                ControlsButton::Back =>
+                    game_state.pop().unwrap(),
+            }
+
+            *color = button_colors::PRESSED_BUTTON.into();
+        }
+        Interaction::Hovered => {
+            *color = button_colors::HOVERED_BUTTON.into();
+        }
+        Interaction::None => {
+            *color = button_colors::NORMAL_BUTTON.into();
+        }
+    });
+}
+
+/// Binds the first key or gamepad button pressed while a binding waits for input. The maps are
+/// resources every game reads directly, so the new binding works right away.
+fn capture_binding(
+    mut capturing: ResMut<Capturing>,
+    edited: Res<EditedPlayer>,
+    mut action_map: ResMut<ActionMap>,
+    mut second_map: ResMut<SecondPlayerActionMap>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>
+) {
+
+    let (action, kind) = match capturing.0 {
+        Some(capturing) => capturing,
+        None => return,
+    };
+
+    let binding = match kind {
+        BindingKind::Key => keys.get_just_pressed().next().map(|key| Binding::Key(*key)),
+        BindingKind::GamepadButton => buttons
+            .get_just_pressed()
+            .next()
+            .map(|button| Binding::GamepadButton(button.1)),
+    };
+
+    if let Some(binding) = binding {
+        let map = edited.map(&mut action_map, &mut second_map);
+        map.rebind(action, binding);
+        map.save(edited.0);
+        capturing.0 = None;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_texts(
+    action_map: Res<ActionMap>,
+    second_map: Res<SecondPlayerActionMap>,
+    capturing: Res<Capturing>,
+    edited: Res<EditedPlayer>,
+    mut binding_query: Query<(&mut Text, &BindingText), (Without<StatusText>, Without<PlayerText>)>,
+    mut status_query: Query<&mut Text, (With<StatusText>, Without<PlayerText>)>,
+    mut player_query: Query<&mut Text, With<PlayerText>>
+) {
+
+    if !action_map.is_changed() && !second_map.is_changed() && !capturing.is_changed() && !edited.is_changed() {
+        return;
+    }
+
+    player_query.single_mut().sections[0].value = format!("Player {}", edited.0 + 1);
+
+    let (action_map, other_map) = match edited.0 {
+        0 => (&*action_map, &second_map.0),
+        _ => (&second_map.0, &*action_map),
+    };
+    let conflicts = action_map.conflicts();
+    // Both players type on the same keyboard.
+    let shared_keys = action_map.shared_keys(other_map);
+    let other_player = match edited.0 {
+        0 => 2,
+        _ => 1,
+    };
+
+    binding_query.for_each_mut(|(mut text, binding_text)| {
+        let BindingText(action, kind) = *binding_text;
+        let binding = action_map.bindings(action).iter().find(|binding| kind.matches(binding));
+
+        let (value, color) = if capturing.0 == Some((action, kind)) {
+            match kind {
+                BindingKind::Key => ("Press a key".to_string(), CAPTURE_COLOR),
+                BindingKind::GamepadButton => ("Press a button".to_string(), CAPTURE_COLOR),
+            }
+        } else {
+            match binding {
+                Some(binding) if conflicts.iter().any(|(conflict, _)| conflict == binding)
+                    || shared_keys.iter().any(|(shared, _)| shared == binding) =>
+                    (binding.name(), CONFLICT_COLOR),
+                Some(binding) => (binding.name(), TEXT_COLOR),
+                None => ("-".to_string(), TEXT_COLOR),
+            }
+        };
+
+        text.sections[0].value = value;
+        text.sections[0].style.color = color;
+    });
+
+    let mut status = status_query.single_mut();
+    status.sections[0].value = match (conflicts.first(), shared_keys.first()) {
+        (Some((binding, actions)), _) => {
+            let names: Vec<&str> = actions.iter().map(|action| action.name()).collect();
+            format!("{} is bound to {}", binding.name(), names.join(" and "))
+        },
+        (None, Some((binding, action))) =>
+            format!("{} is also bound to {} for player {}", binding.name(), action.name(), other_player),
+        (None, None) if capturing.0.is_some() => "Click the binding again to cancel".to_string(),
+        (None, None) => "Click a binding to change it".to_string(),
+    };
+    status.sections[0].style.color = if conflicts.is_empty() && shared_keys.is_empty() { TEXT_COLOR } else { CONFLICT_COLOR };
+}
+
+fn spawn_button(commands: &mut ChildBuilder, font: Handle<Font>, button_type: ControlsButton) {
+    commands
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(220.0), Val::Px(44.0)),
+                margin: Rect::all(Val::Px(5.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            color: button_colors::NORMAL_BUTTON.into(),
+            ..Default::default()
+        })
+        .insert(button_type)
+        .with_children(|parent| {
+            let mut text = parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    match button_type {
+                        // Filled in by `update_texts`.
+                        ControlsButton::Rebind(..) | ControlsButton::SwitchPlayer => "",
+                        ControlsButton::ResetDefaults => "Reset defaults",
+                        ControlsButton::Back => "Back",
+                    },
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 28.0,
+                        color: TEXT_COLOR,
+                    },
+                    Default::default()
+                ),
+                ..Default::default()
+            });
+
+            match button_type {
+                ControlsButton::Rebind(action, kind) => {
+                    text.insert(BindingText(action, kind));
+                },
+                ControlsButton::SwitchPlayer => {
+                    text.insert(PlayerText);
+                },
+                _ => {},
+            }
+        });
+}
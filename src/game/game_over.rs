@@ -0,0 +1,209 @@
+use bevy::prelude::*;
+use std::time::Duration;
+use crate::game::{button_colors, GameState};
+
+/// Final results of a run, inserted by the game right before it enters [`GameState::GameOver`].
+pub(in crate::game) struct GameResults {
+    pub score: u32,
+    /// The share of `score` of every player, only broken down when more than one played.
+    pub player_scores: Vec<u32>,
+    pub destroyed: u32,
+    pub accuracy: f32,
+    pub time_survived: Duration,
+    /// Starting the game with `--seed <number>` set to this plays the same asteroids and enemies again.
+    pub seed: u64,
+    /// The player who took a versus match, with the rounds every player won.
+    pub winner: Option<usize>,
+    pub rounds_won: Vec<u32>,
+}
+
+#[derive(Component)]
+struct GameOverItem;
+
+#[derive(Component, Clone, Copy)]
+enum GameOverButton {
+    Retry,
+    MainMenu,
+}
+
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver)
+                    .with_system(on_enter)
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::GameOver)
+                    .with_system(on_exit)
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::GameOver)
+                    .with_system(handle_buttons)
+            );
+    }
+}
+
+fn on_enter(mut commands: Commands, asset_server: Res<AssetServer>, results: Res<GameResults>) {
+
+    let font: Handle<Font> = asset_server.load("fonts/Regular.ttf");
+
+    commands
+        .spawn_bundle(UiCameraBundle::default())
+        .insert(GameOverItem);
+
+    let seconds = results.time_survived.as_secs();
+    let title = match results.winner {
+        Some(winner) => format!("Player {} wins", winner + 1),
+        None => "Game Over".to_string(),
+    };
+
+    let mut lines = match results.winner {
+        Some(_) => vec![format!("Rounds: {}", results.rounds_won.iter().map(u32::to_string).collect::<Vec<_>>().join(" - "))],
+        None => vec![format!("Score: {}", results.score)],
+    };
+
+    if results.player_scores.len() > 1 {
+        lines.extend(results.player_scores.iter().enumerate().map(|(index, score)| format!("Player {}: {}", index + 1, score)));
+    }
+
+    lines.extend([
+        format!("Asteroids destroyed: {}", results.destroyed),
+        format!("Accuracy: {:.0}%", results.accuracy * 100.0),
+        format!("Time survived: {}:{:02}", seconds / 60, seconds % 60),
+        format!("Seed: {}", results.seed),
+    ]);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::FlexStart,
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            color: Color::rgba(0.2, 0.2, 0.2, 0.8).into(),
+            ..Default::default()
+        })
+        .insert(GameOverItem)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        title,
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 80.0,
+                            color: Color::rgb(0.9, 0.9, 0.9)
+                        },
+                        Default::default()
+                    ),
+                    style: Style {
+                        margin: Rect {
+                            top: Val::Px(100.0),
+                            bottom: Val::Px(40.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                });
+
+            for line in lines {
+                parent
+                    .spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            line,
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 35.0,
+                                color: Color::rgb(0.9, 0.9, 0.9)
+                            },
+                            Default::default()
+                        ),
+                        style: Style {
+                            margin: Rect::all(Val::Px(5.0)),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    });
+            }
+
+            spawn_button(parent, font.clone(), GameOverButton::Retry);
+            spawn_button(parent, font.clone(), GameOverButton::MainMenu);
+        });
+}
+
+fn on_exit(mut commands: Commands, query: Query<Entity, With<GameOverItem>>) {
+
+    query.for_each(|entity| {
+        commands.entity(entity).despawn_recursive();
+    });
+
+    commands.remove_resource::<GameResults>();
+}
+
+fn handle_buttons(
+    mut game_state: ResMut<State<GameState>>,
+    mut query: Query<(&Interaction, &mut UiColor, &GameOverButton)>,
+) {
+
+    query.for_each_mut(|(interaction, mut color, game_over_button)| match interaction {
+        Interaction::Clicked => {
+
+            // Replacing the whole stack also exits the finished run below the overlay.
+            match game_over_button {
+                GameOverButton::Retry =>
+                    game_state.replace(GameState::Asteroids).unwrap(),
+                GameOverButton::MainMenu =>
+                    game_state.replace(GameState::Menu).unwrap(),
+            }
+
+            *color = button_colors::PRESSED_BUTTON.into();
+        }
+        Interaction::Hovered => {
+            *color = button_colors::HOVERED_BUTTON.into();
+        }
+        Interaction::None => {
+            *color = button_colors::NORMAL_BUTTON.into();
+        }
+    });
+}
+
+fn spawn_button(commands: &mut ChildBuilder, font: Handle<Font>, button_type: GameOverButton) {
+    commands
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(200.0), Val::Px(50.0)),
+                margin: Rect::all(Val::Px(10.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            color: button_colors::NORMAL_BUTTON.into(),
+            ..Default::default()
+        })
+        .insert(button_type)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        match button_type {
+                            GameOverButton::Retry => "Retry",
+                            GameOverButton::MainMenu => "Main Menu",
+                        },
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 35.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                        },
+                        Default::default()
+                    ),
+                    ..Default::default()
+                });
+        });
+}
@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+use bevy::ui::UiSystem;
+use std::cmp::Ordering;
+
+/// The button picked with the D-pad, it stays highlighted until the mouse is moved.
+#[derive(Default)]
+struct GamepadFocus(Option<Entity>);
+
+/// Lets the D-pad move between the buttons of any screen and South press the highlighted one.
+pub(crate) struct GamepadNavigationPlugin;
+
+impl Plugin for GamepadNavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<GamepadFocus>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                navigate_buttons.after(UiSystem::Focus)
+            );
+    }
+}
+
+/// Runs right after the mouse has updated the buttons, so the screens see a gamepad click
+/// exactly like a mouse click.
+fn navigate_buttons(
+    mut focus: ResMut<GamepadFocus>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    buttons: Res<Input<GamepadButton>>,
+    mut query: Query<(Entity, &GlobalTransform, &mut Interaction), With<Button>>,
+    parents: Query<&Parent>
+) {
+
+    if cursor_moved.iter().count() > 0 {
+        // Hand the button back to the mouse, without leaving it pressed.
+        if let Some((_, _, mut interaction)) = focus.0.take().and_then(|button| query.get_mut(button).ok()) {
+            *interaction = Interaction::None;
+        }
+        return;
+    }
+
+    let just_pressed = |button_type| buttons.get_just_pressed().any(|button| button.1 == button_type);
+    let step = if just_pressed(GamepadButtonType::DPadUp) || just_pressed(GamepadButtonType::DPadLeft) {
+        -1
+    } else if just_pressed(GamepadButtonType::DPadDown) || just_pressed(GamepadButtonType::DPadRight) {
+        1
+    } else {
+        0
+    };
+
+    let root = |mut entity: Entity| {
+        while let Ok(parent) = parents.get(entity) {
+            entity = parent.0;
+        }
+        entity
+    };
+
+    // Screens like the controls are opened on top of another one, only the buttons of the screen
+    // drawn in front can be reached.
+    let front = query
+        .iter()
+        .max_by(|(_, a, _), (_, b, _)| a.translation.z.partial_cmp(&b.translation.z).unwrap_or(Ordering::Equal))
+        .map(|(entity, _, _)| root(entity));
+
+    // Top to bottom, then left to right, the order the buttons are read in.
+    let mut order: Vec<(Entity, Vec3)> = query
+        .iter()
+        .filter(|(entity, _, _)| Some(root(*entity)) == front)
+        .map(|(entity, transform, _)| (entity, transform.translation))
+        .collect();
+    order.sort_by(|(_, a), (_, b)| b.y.partial_cmp(&a.y).unwrap_or(Ordering::Equal).then(a.x.partial_cmp(&b.x).unwrap_or(Ordering::Equal)));
+
+    if order.is_empty() {
+        focus.0 = None;
+        return;
+    }
+
+    let current = focus.0.and_then(|button| order.iter().position(|(entity, _)| *entity == button));
+    let index = match (current, step) {
+        (Some(index), step) => (index as i32 + step).rem_euclid(order.len() as i32) as usize,
+        // The first press only shows where the focus is.
+        (None, step) if step != 0 => 0,
+        (None, _) => return,
+    };
+
+    let button = order[index].0;
+    focus.0 = Some(button);
+
+    // A click only lasts one frame, the mouse would release it the same way.
+    if let Ok((_, _, mut interaction)) = query.get_mut(button) {
+        let target = if just_pressed(GamepadButtonType::South) { Interaction::Clicked } else { Interaction::Hovered };
+
+        if *interaction != target {
+            *interaction = target;
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use bevy::prelude::*;
 use bevy::app::AppExit;
 use crate::game::{button_colors, GameState};
+use crate::game::asteroids_game::{ArenaMode, MatchMode};
 
 #[derive(Component)]
 struct MenuItem;
@@ -8,10 +9,19 @@ struct MenuItem;
 #[derive(Component, Clone, Copy)]
 enum MenuButton {
     Asteroids,
+    ArenaMode,
+    MatchMode,
+    WatchReplay,
     Settings,
     Quit,
 }
 
+#[derive(Component)]
+struct ArenaModeText;
+
+#[derive(Component)]
+struct MatchModeText;
+
 pub(crate) struct MenuPlugin;
 
 impl Plugin for MenuPlugin {
@@ -28,11 +38,15 @@ impl Plugin for MenuPlugin {
             .add_system_set(
                 SystemSet::on_update(GameState::Menu)
                     .with_system(handle_buttons)
+                    .with_system(handle_arena_mode_button)
+                    .with_system(handle_match_mode_button
+                                 .after(handle_arena_mode_button)
+                    )
             );
     }
 }
 
-fn on_enter(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn on_enter(mut commands: Commands, asset_server: Res<AssetServer>, arena_mode: Res<ArenaMode>, match_mode: Res<MatchMode>) {
 
     let font: Handle<Font> = asset_server.load("fonts/Regular.ttf");
 
@@ -76,9 +90,12 @@ fn on_enter(mut commands: Commands, asset_server: Res<AssetServer>) {
                             ..Default::default()
                         })
                         .with_children(|parent| {
-                            spawn_button(parent, font.clone(), MenuButton::Asteroids);
-                            spawn_button(parent, font.clone(), MenuButton::Settings);
-                            spawn_button(parent, font.clone(), MenuButton::Quit);
+                            spawn_button(parent, font.clone(), MenuButton::Asteroids, *arena_mode, *match_mode);
+                            spawn_button(parent, font.clone(), MenuButton::ArenaMode, *arena_mode, *match_mode);
+                            spawn_button(parent, font.clone(), MenuButton::MatchMode, *arena_mode, *match_mode);
+                            spawn_button(parent, font.clone(), MenuButton::WatchReplay, *arena_mode, *match_mode);
+                            spawn_button(parent, font.clone(), MenuButton::Settings, *arena_mode, *match_mode);
+                            spawn_button(parent, font.clone(), MenuButton::Quit, *arena_mode, *match_mode);
                         });
 
                 });
@@ -132,6 +149,9 @@ fn handle_buttons(
             match menu_button {
                 MenuButton::Asteroids =>
                     game_state.set(GameState::Asteroids).unwrap(),
+                MenuButton::ArenaMode | MenuButton::MatchMode => {},
+                MenuButton::WatchReplay =>
+                    game_state.set(GameState::Replays).unwrap(),
                 MenuButton::Settings =>
                     game_state.set(GameState::Settings).unwrap(),
                 MenuButton::Quit =>
@@ -149,7 +169,60 @@ fn handle_buttons(
     });
 }
 
-fn spawn_button(commands: &mut ChildBuilder, font: Handle<Font>, button_type: MenuButton) {
+/// Cycles the arena mode once per click, the other buttons leave the menu anyway.
+/// Versus is always played in the classic arena.
+fn handle_arena_mode_button(
+    mut arena_mode: ResMut<ArenaMode>,
+    match_mode: Res<MatchMode>,
+    button_query: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut text_query: Query<&mut Text, With<ArenaModeText>>
+) {
+
+    if *match_mode == MatchMode::Versus {
+        return;
+    }
+
+    button_query.for_each(|(interaction, menu_button)| {
+        if let (Interaction::Clicked, MenuButton::ArenaMode) = (interaction, menu_button) {
+            *arena_mode = arena_mode.next();
+
+            text_query.for_each_mut(|mut text| {
+                text.sections[0].value = arena_mode.name().to_string();
+            });
+        }
+    });
+}
+
+/// Cycles between playing alone, together and against each other, like the arena mode.
+/// Picking versus switches to the classic arena, the ships need the walls to wrap around.
+fn handle_match_mode_button(
+    mut match_mode: ResMut<MatchMode>,
+    mut arena_mode: ResMut<ArenaMode>,
+    button_query: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut text_query: Query<&mut Text, (With<MatchModeText>, Without<ArenaModeText>)>,
+    mut arena_text_query: Query<&mut Text, With<ArenaModeText>>
+) {
+
+    button_query.for_each(|(interaction, menu_button)| {
+        if let (Interaction::Clicked, MenuButton::MatchMode) = (interaction, menu_button) {
+            *match_mode = match_mode.next();
+
+            text_query.for_each_mut(|mut text| {
+                text.sections[0].value = match_mode.name().to_string();
+            });
+
+            if *match_mode == MatchMode::Versus {
+                *arena_mode = ArenaMode::Wrap;
+
+                arena_text_query.for_each_mut(|mut text| {
+                    text.sections[0].value = arena_mode.name().to_string();
+                });
+            }
+        }
+    });
+}
+
+fn spawn_button(commands: &mut ChildBuilder, font: Handle<Font>, button_type: MenuButton, arena_mode: ArenaMode, match_mode: MatchMode) {
     commands.spawn_bundle(ButtonBundle {
         style: Style {
             size: Size::new(Val::Px(170.0), Val::Px(50.0)),
@@ -163,10 +236,13 @@ fn spawn_button(commands: &mut ChildBuilder, font: Handle<Font>, button_type: Me
     })
     .insert(button_type)
     .with_children(|parent| {
-        parent.spawn_bundle(TextBundle {
+        let mut text = parent.spawn_bundle(TextBundle {
             text: Text::with_section(
                 match button_type {
                     MenuButton::Asteroids => "Asteroids",
+                    MenuButton::ArenaMode => arena_mode.name(),
+                    MenuButton::MatchMode => match_mode.name(),
+                    MenuButton::WatchReplay => "Watch replay",
                     MenuButton::Settings => "Settings",
                     MenuButton::Quit => "Quit",
                 },
@@ -179,5 +255,11 @@ fn spawn_button(commands: &mut ChildBuilder, font: Handle<Font>, button_type: Me
             ),
             ..Default::default()
         });
+
+        match button_type {
+            MenuButton::ArenaMode => { text.insert(ArenaModeText); },
+            MenuButton::MatchMode => { text.insert(MatchModeText); },
+            _ => {},
+        }
     });
 }
@@ -1,9 +1,14 @@
 use bevy::{prelude::*, app::PluginGroupBuilder};
 
+mod actions;
 mod asteroids_game;
+mod controls;
+mod game_over;
+mod gamepad_navigation;
 mod menu;
 mod settings;
 mod pause;
+mod replays;
 
 mod button_colors {
     use bevy::prelude::Color;
@@ -14,10 +19,13 @@ mod button_colors {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
-pub(in self) enum GameState {
+enum GameState {
     Menu,
     Settings,
+    Controls,
+    Replays,
     Pause,
+    GameOver,
     Asteroids,
 }
 
@@ -25,7 +33,11 @@ struct BasePlugin;
 
 impl Plugin for BasePlugin {
     fn build(&self, app: &mut App) {
-        app.add_state(GameState::Menu);
+        app
+            .insert_resource(actions::ActionMap::load(0))
+            .insert_resource(actions::SecondPlayerActionMap(actions::ActionMap::load(1)))
+            .init_resource::<actions::ControlScheme>()
+            .add_state(GameState::Menu);
     }
 }
 
@@ -38,6 +50,10 @@ impl PluginGroup for GamePlugins {
             .add(asteroids_game::AsteroidsPlugin)
             .add(menu::MenuPlugin)
             .add(pause::PausePlugin)
-            .add(settings::SettingsPlugin);
+            .add(game_over::GameOverPlugin)
+            .add(settings::SettingsPlugin)
+            .add(controls::ControlsPlugin)
+            .add(replays::ReplaysPlugin)
+            .add(gamepad_navigation::GamepadNavigationPlugin);
     }
 }
@@ -1,6 +1,7 @@
 use bevy::prelude::*;
 use std::time::Duration;
 use crate::game::{button_colors, GameState};
+use crate::game::actions::{Action, Actions};
 
 const MINIMUM_TIME: Duration = Duration::from_millis(200);
 const PAUSE_COOLDOWN: Duration = Duration::from_millis(200);
@@ -14,6 +15,7 @@ struct PauseItem;
 #[derive(Component, Clone, Copy)]
 enum PauseButton {
     Continue,
+    Controls,
     Quit,
 }
 
@@ -81,13 +83,14 @@ fn on_enter(mut commands: Commands, asset_server: Res<AssetServer>, time: Res<Ti
                     ..Default::default()
                 });
             spawn_button(parent, font.clone(), PauseButton::Continue);
+            spawn_button(parent, font.clone(), PauseButton::Controls);
             spawn_button(parent, font.clone(), PauseButton::Quit);
         });
 }
 
-fn handle_keyboard(keys: Res<Input<KeyCode>>, mut game_state: ResMut<State<GameState>>, time: Res<Time>, entered: Res<PauseEntered>) {
+fn handle_keyboard(actions: Actions, mut game_state: ResMut<State<GameState>>, time: Res<Time>, entered: Res<PauseEntered>) {
 
-    if time.time_since_startup() - entered.0 > MINIMUM_TIME && keys.just_pressed(KeyCode::Escape) {
+    if time.time_since_startup() - entered.0 > MINIMUM_TIME && actions.just_pressed(Action::Pause) {
         game_state.pop().unwrap();
     }
 }
@@ -104,12 +107,23 @@ fn on_exit(mut commands: Commands, query: Query<Entity, With<PauseItem>>, time:
 
 pub(in crate::game) fn handle_start_pause(
     mut commands: Commands,
-    keys: Res<Input<KeyCode>>,
+    actions: Actions,
+    mut gamepad_events: EventReader<GamepadEvent>,
     mut game_state: ResMut<State<GameState>>,
     exited: Option<Res<PauseExited>>,
     time: Res<Time>
 ) {
 
+    // Losing the controller pauses right away, even while the pause button is cooling down.
+    if gamepad_events.iter().any(|GamepadEvent(_, event_type)| *event_type == GamepadEventType::Disconnected) {
+        commands.remove_resource::<PauseExited>();
+        // The game may already be moving on to another state this frame, like the game over screen.
+        if let Err(error) = game_state.push(GameState::Pause) {
+            info!("Not pausing for the disconnected controller: {}", error);
+        }
+        return;
+    }
+
     match exited {
         Some(exit_time) => {
             if time.time_since_startup() - exit_time.0 > PAUSE_COOLDOWN {
@@ -117,7 +131,7 @@ pub(in crate::game) fn handle_start_pause(
             }
         },
         None => {
-            if keys.just_pressed(KeyCode::Escape) {
+            if actions.just_pressed(Action::Pause) {
                 game_state.push(GameState::Pause).unwrap();
             }
         },
@@ -136,6 +150,8 @@ fn handle_buttons(
             match pause_button {
                 PauseButton::Continue =>
                     game_state.pop().unwrap(),
+                PauseButton::Controls =>
+                    game_state.push(GameState::Controls).unwrap(),
                 PauseButton::Quit =>
                     game_state.replace(GameState::Menu).unwrap(),
             }
@@ -171,6 +187,7 @@ fn spawn_button(commands: &mut ChildBuilder, font: Handle<Font>, button_type: Pa
                     text: Text::with_section(
                         match button_type {
                             PauseButton::Continue => "Continue",
+                            PauseButton::Controls => "Controls",
                             PauseButton::Quit => "Quit",
                         },
                         TextStyle {
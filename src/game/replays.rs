@@ -0,0 +1,199 @@
+use bevy::prelude::*;
+use crate::game::{button_colors, GameState};
+use crate::game::asteroids_game::{list_replays, ArenaMode, MatchMode, Replay, ReplaySelection};
+
+const MAX_LISTED: usize = 8;
+
+#[derive(Component)]
+struct ReplaysItem;
+
+#[derive(Component, Clone, Copy)]
+enum ReplaysButton {
+    Watch(usize),
+    Back,
+}
+
+/// The replays shown on the screen, in the same order as their buttons.
+struct ReplayList(Vec<Replay>);
+
+pub(crate) struct ReplaysPlugin;
+
+impl Plugin for ReplaysPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system_set(
+                SystemSet::on_enter(GameState::Replays)
+                    .with_system(on_enter)
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Replays)
+                    .with_system(on_exit)
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Replays)
+                    .with_system(handle_buttons)
+            );
+    }
+}
+
+fn on_enter(mut commands: Commands, asset_server: Res<AssetServer>) {
+
+    let font: Handle<Font> = asset_server.load("fonts/Regular.ttf");
+
+    let mut labels = Vec::new();
+    let mut replays = Vec::new();
+
+    for path in list_replays().into_iter().take(MAX_LISTED) {
+        match Replay::load(&path) {
+            Ok(replay) => {
+                let seconds = replay.duration().as_secs();
+                let name = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+
+                labels.push(format!("{}   {}   {}   {}:{:02}", name, replay.arena_mode().name(), replay.match_mode().name(), seconds / 60, seconds % 60));
+                replays.push(replay);
+            },
+            Err(error) => warn!("Skipping replay {}: {}", path.display(), error),
+        }
+    }
+
+    commands
+        .spawn_bundle(UiCameraBundle::default())
+        .insert(ReplaysItem);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::FlexStart,
+                ..Default::default()
+            },
+            color: Color::BLACK.into(),
+            ..Default::default()
+        })
+        .insert(ReplaysItem)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "Replays",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 80.0,
+                            color: Color::rgb(0.9, 0.9, 0.9)
+                        },
+                        Default::default()
+                    ),
+                    style: Style {
+                        margin: Rect {
+                            top: Val::Px(60.0),
+                            bottom: Val::Px(30.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                });
+
+            if labels.is_empty() {
+                parent
+                    .spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "No saved replays yet, finish a run to record one.",
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 30.0,
+                                color: Color::rgb(0.6, 0.6, 0.6)
+                            },
+                            Default::default()
+                        ),
+                        style: Style {
+                            margin: Rect::all(Val::Px(10.0)),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    });
+            }
+
+            for (index, label) in labels.iter().enumerate() {
+                spawn_button(parent, font.clone(), ReplaysButton::Watch(index), label);
+            }
+
+            spawn_button(parent, font.clone(), ReplaysButton::Back, "Back");
+        });
+
+    commands.insert_resource(ReplayList(replays));
+}
+
+fn on_exit(mut commands: Commands, query: Query<Entity, With<ReplaysItem>>) {
+
+    query.for_each(|entity| {
+        commands.entity(entity).despawn_recursive();
+    });
+
+    commands.remove_resource::<ReplayList>();
+}
+
+fn handle_buttons(
+    mut game_state: ResMut<State<GameState>>,
+    mut query: Query<(&Interaction, &mut UiColor, &ReplaysButton)>,
+    replay_list: Res<ReplayList>,
+    mut selection: ResMut<ReplaySelection>,
+    mut arena_mode: ResMut<ArenaMode>,
+    mut match_mode: ResMut<MatchMode>
+) {
+
+    query.for_each_mut(|(interaction, mut color, replays_button)| match interaction {
+        Interaction::Clicked => {
+
+            match replays_button {
+                ReplaysButton::Watch(index) => {
+                    selection.watch(replay_list.0[*index].clone(), &mut arena_mode, &mut match_mode);
+                    game_state.set(GameState::Asteroids).unwrap();
+                },
+                ReplaysButton::Back =>
+                    game_state.set(GameState::Menu).unwrap(),
+            }
+
+            *color = button_colors::PRESSED_BUTTON.into();
+        }
+        Interaction::Hovered => {
+            *color = button_colors::HOVERED_BUTTON.into();
+        }
+        Interaction::None => {
+            *color = button_colors::NORMAL_BUTTON.into();
+        }
+    });
+}
+
+fn spawn_button(commands: &mut ChildBuilder, font: Handle<Font>, button_type: ReplaysButton, label: &str) {
+    commands
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(500.0), Val::Px(50.0)),
+                margin: Rect::all(Val::Px(8.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            color: button_colors::NORMAL_BUTTON.into(),
+            ..Default::default()
+        })
+        .insert(button_type)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        label,
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 30.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                        },
+                        Default::default()
+                    ),
+                    ..Default::default()
+                });
+        });
+}
@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 use crate::game::{button_colors, GameState};
+use crate::game::actions::ControlScheme;
+use crate::game::asteroids_game::RadarRange;
 
 #[derive(Component)]
 struct SettingsItem;
@@ -9,12 +11,21 @@ enum SettingsButton {
     NextSize,
     PreviousSize,
     Apply,
+    ControlScheme,
+    Controls,
+    RadarRange,
     Exit,
 }
 
 #[derive(Component, Clone, Copy)]
 struct ScreenSizeDisplay;
 
+#[derive(Component, Clone, Copy)]
+struct ControlSchemeDisplay;
+
+#[derive(Component, Clone, Copy)]
+struct RadarRangeDisplay;
+
 #[derive(Clone, Copy)]
 enum ScreenSize {
     Size1280x1024,
@@ -49,7 +60,7 @@ fn insert_resource(mut commands: Commands) {
     commands.insert_resource(ScreenSize::Size1280x1024);
 }
 
-fn spawn_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn spawn_ui(mut commands: Commands, asset_server: Res<AssetServer>, control_scheme: Res<ControlScheme>, radar_range: Res<RadarRange>) {
 
     let font_handle = asset_server.load("fonts/Regular.ttf");
 
@@ -136,8 +147,8 @@ fn spawn_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
                                             ..Default::default()
                                         })
                                         .with_children(|parent| {
-                                            spawn_button(parent, font_handle.clone(), SettingsButton::PreviousSize);
-                                            spawn_button(parent, font_handle.clone(), SettingsButton::NextSize);
+                                            spawn_button(parent, font_handle.clone(), SettingsButton::PreviousSize, *control_scheme, *radar_range);
+                                            spawn_button(parent, font_handle.clone(), SettingsButton::NextSize, *control_scheme, *radar_range);
                                         });
                                     parent
                                         .spawn_bundle(TextBundle {
@@ -158,10 +169,50 @@ fn spawn_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
                                         })
                                         .insert(ScreenSizeDisplay);
 
-                                    spawn_button(parent, font_handle.clone(), SettingsButton::Apply);
+                                    spawn_button(parent, font_handle.clone(), SettingsButton::Apply, *control_scheme, *radar_range);
                                 });
 
-                            spawn_button(parent, font_handle.clone(), SettingsButton::Exit);
+                            parent
+                                .spawn_bundle(TextBundle {
+                                    text: Text::with_section(
+                                        "Control scheme",
+                                        TextStyle {
+                                            font: font_handle.clone(),
+                                            font_size: 35.0,
+                                            color: Color::rgb(0.5, 0.5, 0.5)
+                                        },
+                                        Default::default()
+                                    ),
+                                    style: Style {
+                                        margin: Rect::all(Val::Px(10.0)),
+                                        ..Default::default()
+                                    },
+                                    ..Default::default()
+                                });
+
+                            spawn_button(parent, font_handle.clone(), SettingsButton::ControlScheme, *control_scheme, *radar_range);
+                            spawn_button(parent, font_handle.clone(), SettingsButton::Controls, *control_scheme, *radar_range);
+
+                            parent
+                                .spawn_bundle(TextBundle {
+                                    text: Text::with_section(
+                                        "Radar range",
+                                        TextStyle {
+                                            font: font_handle.clone(),
+                                            font_size: 35.0,
+                                            color: Color::rgb(0.5, 0.5, 0.5)
+                                        },
+                                        Default::default()
+                                    ),
+                                    style: Style {
+                                        margin: Rect::all(Val::Px(10.0)),
+                                        ..Default::default()
+                                    },
+                                    ..Default::default()
+                                });
+
+                            spawn_button(parent, font_handle.clone(), SettingsButton::RadarRange, *control_scheme, *radar_range);
+                            spawn_button(parent, font_handle.clone(), SettingsButton::Exit, *control_scheme, *radar_range);
                         });
                 });
         });
@@ -173,11 +224,16 @@ fn remove_ui(mut commands: Commands, query: Query<Entity, With<SettingsItem>>) {
     });
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn handle_buttons(
     mut button_query: Query<(&Interaction, &mut UiColor, &SettingsButton), Changed<Interaction>>,
-    mut display_text_query: Query<&mut Text, With<ScreenSizeDisplay>>,
+    mut display_text_query: Query<&mut Text, (With<ScreenSizeDisplay>, Without<ControlSchemeDisplay>, Without<RadarRangeDisplay>)>,
+    mut control_scheme_text_query: Query<&mut Text, (With<ControlSchemeDisplay>, Without<RadarRangeDisplay>)>,
+    mut radar_range_text_query: Query<&mut Text, With<RadarRangeDisplay>>,
     mut game_state: ResMut<State<GameState>>,
     mut screen_size: ResMut<ScreenSize>,
+    mut control_scheme: ResMut<ControlScheme>,
+    mut radar_range: ResMut<RadarRange>,
     mut windows: ResMut<Windows>
 ) {
 
@@ -188,18 +244,32 @@ fn handle_buttons(
                 SettingsButton::NextSize => {
                     *screen_size = next_screen_size(&screen_size);
                     let mut text = display_text_query.single_mut();
-                    text.sections[0].value = text_for_screen_size(&*screen_size);
+                    text.sections[0].value = text_for_screen_size(&screen_size);
                 },
                 SettingsButton::PreviousSize => {
                     *screen_size = previous_screen_size(&screen_size);
                     let mut text = display_text_query.single_mut();
-                    text.sections[0].value = text_for_screen_size(&*screen_size);
+                    text.sections[0].value = text_for_screen_size(&screen_size);
                 },
                 SettingsButton::Apply => {
                     let window = windows.get_primary_mut().unwrap();
                     let (width, height) = resoultion_for_screen_size(&screen_size);
                     window.set_resolution(width, height);
                 },
+                SettingsButton::ControlScheme => {
+                    *control_scheme = control_scheme.next();
+                    let mut text = control_scheme_text_query.single_mut();
+                    text.sections[0].value = control_scheme.name().to_string();
+                },
+                SettingsButton::Controls => {
+                    game_state.push(GameState::Controls).unwrap();
+                },
+                SettingsButton::RadarRange => {
+                    *radar_range = radar_range.next();
+                    radar_range.save();
+                    let mut text = radar_range_text_query.single_mut();
+                    text.sections[0].value = radar_range.name().to_string();
+                },
                 SettingsButton::Exit => {
                     game_state.set(GameState::Menu).unwrap();
                 },
@@ -216,10 +286,22 @@ fn handle_buttons(
     });
 }
 
-fn spawn_button(commands: &mut ChildBuilder, font: Handle<Font>, button_type: SettingsButton) {
+fn spawn_button(
+    commands: &mut ChildBuilder,
+    font: Handle<Font>,
+    button_type: SettingsButton,
+    control_scheme: ControlScheme,
+    radar_range: RadarRange
+) {
+
+    let width = match button_type {
+        SettingsButton::ControlScheme | SettingsButton::Controls | SettingsButton::RadarRange => 160.0,
+        _ => 100.0,
+    };
+
     commands.spawn_bundle(ButtonBundle {
         style: Style {
-            size: Size::new(Val::Px(100.0), Val::Px(50.0)),
+            size: Size::new(Val::Px(width), Val::Px(50.0)),
             margin: Rect::all(Val::Px(10.0)),
             justify_content: JustifyContent::Center,
             align_items: AlignItems::Center,
@@ -230,12 +312,15 @@ fn spawn_button(commands: &mut ChildBuilder, font: Handle<Font>, button_type: Se
     })
     .insert(button_type)
     .with_children(|parent| {
-        parent.spawn_bundle(TextBundle {
+        let mut text = parent.spawn_bundle(TextBundle {
             text: Text::with_section(
                 match button_type {
                     SettingsButton::NextSize => "=>",
                     SettingsButton::PreviousSize => "<=",
                     SettingsButton::Apply => "Apply",
+                    SettingsButton::ControlScheme => control_scheme.name(),
+                    SettingsButton::Controls => "Controls",
+                    SettingsButton::RadarRange => radar_range.name(),
                     SettingsButton::Exit => "Exit",
                 },
                 TextStyle {
@@ -247,6 +332,16 @@ fn spawn_button(commands: &mut ChildBuilder, font: Handle<Font>, button_type: Se
             ),
             ..Default::default()
         });
+
+        match button_type {
+            SettingsButton::ControlScheme => {
+                text.insert(ControlSchemeDisplay);
+            },
+            SettingsButton::RadarRange => {
+                text.insert(RadarRangeDisplay);
+            },
+            _ => {},
+        }
     });
 }
 